@@ -0,0 +1,202 @@
+//! Audio file metadata.
+use std::fs::File;
+
+use lofty::config::ParseOptions;
+use lofty::file::{FileType, TaggedFile};
+use lofty::mp4::{Mp4Codec, Mp4File};
+use lofty::mpeg::{Layer, MpegFile};
+use lofty::prelude::*;
+use lofty::tag::{ItemValue, Tag, TagType};
+use log::*;
+
+use crate::interface::*;
+use crate::mime::mime_db;
+
+/// Audio file backend.
+pub struct AudioBackend;
+
+struct AudioMeta;
+
+impl FileViewer for AudioBackend {
+    fn name(&self) -> &'static str {
+        "audio"
+    }
+
+    fn make_view(&self, req: &FileRequest, mode: &Option<ViewType>) -> Option<Box<dyn FileView>> {
+        let mode = mode.clone().unwrap_or(ViewType::Meta);
+        if mode == ViewType::Meta
+            && req.mime_type.starts_with("audio/")
+            && FileType::from_path(&req.path).is_some()
+        {
+            Some(Box::new(AudioMeta))
+        } else {
+            None
+        }
+    }
+}
+
+impl MetaView for AudioMeta {
+    fn describe(&self, req: &FileRequest, options: &ViewOptions) -> Result<MetaInfo, ViewError> {
+        let db = mime_db();
+        let file = lofty::read_from_path(&req.path).map_err(ViewError::wrap)?;
+        let props = file.properties();
+
+        let mut info = MetaInfo::new(db.description(&req.mime_type).unwrap_or("Audio file"));
+        info.summary = Some(Field::new(
+            "duration",
+            "Duration",
+            Value::Duration(props.duration()),
+        ));
+        info.size = req.file_size();
+
+        info.add("codec", "Codec", codec_name(req, &file));
+        info.add_opt(
+            "sample_rate",
+            "Sample rate",
+            props.sample_rate().map(|r| Value::Count(r.into(), "Hz")),
+        );
+        if let Some(channels) = props.channels() {
+            let layout = match channels {
+                1 => " (mono)",
+                2 => " (stereo)",
+                _ => "",
+            };
+            info.add("channels", "Channels", format!("{}{}", channels, layout));
+        }
+        info.add_opt(
+            "bit_depth",
+            "Bit depth",
+            props.bit_depth().map(|d| Value::Count(d.into(), "bits")),
+        );
+        info.add_opt(
+            "bitrate",
+            "Bitrate",
+            props
+                .audio_bitrate()
+                .or(props.overall_bitrate())
+                .map(|r| Value::Count(r.into(), "kbps")),
+        );
+
+        for tag in file.tags() {
+            info.fields.push(tag_field(tag, options));
+        }
+        Ok(info)
+    }
+}
+
+fn codec_name(req: &FileRequest, file: &TaggedFile) -> String {
+    let parse = ParseOptions::new().read_tags(false);
+    match file.file_type() {
+        FileType::Aac => "AAC (ADTS)".into(),
+        FileType::Aiff => "PCM (AIFF)".into(),
+        FileType::Ape => "Monkey's Audio".into(),
+        FileType::Flac => "FLAC".into(),
+        FileType::Mpeg => {
+            let layer = File::open(&req.path)
+                .ok()
+                .and_then(|mut f| MpegFile::read_from(&mut f, parse).ok())
+                .map(|f| *f.properties().layer());
+            match layer {
+                Some(Layer::Layer1) => "MPEG Audio Layer 1 (MP1)".into(),
+                Some(Layer::Layer2) => "MPEG Audio Layer 2 (MP2)".into(),
+                _ => "MPEG Audio Layer 3 (MP3)".into(),
+            }
+        }
+        FileType::Mp4 => {
+            let codec = File::open(&req.path)
+                .ok()
+                .and_then(|mut f| Mp4File::read_from(&mut f, parse).ok())
+                .and_then(|f| f.properties().codec());
+            match codec {
+                Some(Mp4Codec::AAC) => "AAC (MP4)".into(),
+                Some(Mp4Codec::ALAC) => "Apple Lossless (MP4)".into(),
+                Some(Mp4Codec::MP3) => "MP3 (MP4)".into(),
+                Some(Mp4Codec::FLAC) => "FLAC (MP4)".into(),
+                _ => {
+                    debug!("unknown MP4 audio codec");
+                    "unknown (MP4)".into()
+                }
+            }
+        }
+        FileType::Mpc => "Musepack".into(),
+        FileType::Opus => "Opus".into(),
+        FileType::Vorbis => "Vorbis".into(),
+        FileType::Speex => "Speex".into(),
+        FileType::Wav => "PCM (WAV)".into(),
+        FileType::WavPack => "WavPack".into(),
+        FileType::Custom(name) => name.into(),
+        ft => format!("{:?}", ft),
+    }
+}
+
+fn tag_type_name(tt: TagType) -> &'static str {
+    match tt {
+        TagType::Ape => "APE",
+        TagType::Id3v1 => "ID3v1",
+        TagType::Id3v2 => "ID3v2",
+        TagType::Mp4Ilst => "MP4",
+        TagType::VorbisComments => "Vorbis comment",
+        TagType::RiffInfo => "RIFF INFO",
+        TagType::AiffText => "AIFF text",
+        _ => "unknown",
+    }
+}
+
+fn tag_field(tag: &Tag, options: &ViewOptions) -> Field {
+    let mut fields = vec![Field::new("items", "Items", tag.item_count())];
+
+    if options.long_display {
+        for item in tag.items() {
+            let value = match item.value() {
+                ItemValue::Text(t) | ItemValue::Locator(t) => t.clone(),
+                ItemValue::Binary(b) => format!("<{}>", friendly::bytes(b.len())),
+            };
+            fields.push(Field::from_label(format!("{:?}", item.key()), value));
+        }
+    } else {
+        let values = [
+            ("title", "Title", tag.title()),
+            ("artist", "Artist", tag.artist()),
+            ("album", "Album", tag.album()),
+            ("genre", "Genre", tag.genre()),
+        ];
+        for (key, label, value) in values {
+            if let Some(value) = value {
+                fields.push(Field::new(key, label, value.to_string()));
+            }
+        }
+        if let Some(date) = tag.date() {
+            fields.push(Field::new("date", "Date", date.to_string()));
+        }
+        match (tag.track(), tag.track_total()) {
+            (Some(n), Some(t)) => fields.push(Field::new("track", "Track", format!("{}/{}", n, t))),
+            (Some(n), None) => fields.push(Field::new("track", "Track", n)),
+            _ => (),
+        }
+    }
+
+    let pictures: Vec<Value> = tag
+        .pictures()
+        .iter()
+        .map(|pic| {
+            let mime = pic
+                .mime_type()
+                .map(|m| m.as_str())
+                .unwrap_or("unknown type");
+            Value::Text(format!(
+                "{:?} ({}, {})",
+                pic.pic_type(),
+                mime,
+                friendly::bytes(pic.data().len())
+            ))
+        })
+        .collect();
+    if !pictures.is_empty() {
+        fields.push(Field::new("pictures", "Pictures", Value::List(pictures)));
+    }
+
+    Field::from_label(
+        format!("{} tag", tag_type_name(tag.tag_type())),
+        Value::Record(fields),
+    )
+}
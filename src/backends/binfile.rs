@@ -2,13 +2,10 @@
 
 use log::*;
 
+use crate::interface::*;
 use crate::mime::mime_db;
-use crate::programs::{find_program, run_command};
-use crate::styling::FIELD_NAME;
-use crate::{
-    interface::*,
-    styling::{styled, FILE_SIZE, FILE_TYPE},
-};
+use crate::programs::find_program;
+use crate::styling::FILE_TYPE;
 
 /// Non-specialized text file backend.
 pub struct BinfileBackend {}
@@ -25,6 +22,10 @@ struct FileSummary {
 
 /// Viewer for generic binary files.
 impl FileViewer for BinfileBackend {
+    fn name(&self) -> &'static str {
+        "binary"
+    }
+
     fn make_view(&self, req: &FileRequest, mode: &Option<ViewType>) -> Option<Box<dyn FileView>> {
         let db = mime_db();
         if db.is_subtype(&req.mime_type, "application/octet-stream") {
@@ -43,32 +44,26 @@ impl FileViewer for BinfileBackend {
     }
 }
 
-impl FileView for FileMeta {
-    fn display(&self, req: &FileRequest, _options: &ViewOptions) -> Result<(), ViewError> {
+impl MetaView for FileMeta {
+    fn describe(&self, req: &FileRequest, _options: &ViewOptions) -> Result<MetaInfo, ViewError> {
         let db = mime_db();
-
-        print!("file of type {}", styled(&req.mime_type, &FILE_TYPE));
-
-        if let Some(bytes) = req.file_size() {
-            print!(
-                " ({})",
-                styled(format!("{}", friendly::bytes(bytes)), &FILE_SIZE)
-            );
-        }
-        println!();
-
-        if let Some(desc) = db.description(&req.mime_type) {
-            println!("{}: {}", styled("Type description", &FIELD_NAME), desc);
-        };
-        if let Some(size) = req.file_size() {
-            println!("{}: {}", styled("Size", &FIELD_NAME), friendly::bytes(size));
-        }
-        Ok(())
+        let desc = db
+            .description(&req.mime_type)
+            .filter(|d| *d != "unknown")
+            .unwrap_or("binary file");
+        let mut info = MetaInfo::new(desc);
+        info.size = req.file_size();
+        info.add(
+            "mime_type",
+            "MIME type",
+            Value::Styled(req.mime_type.clone(), &FILE_TYPE),
+        );
+        Ok(info)
     }
 }
 
 impl FileView for HexView {
-    fn display(&self, req: &FileRequest, _options: &ViewOptions) -> Result<(), ViewError> {
+    fn render(&self, req: &FileRequest, _options: &ViewOptions) -> Result<ViewOutput, ViewError> {
         let cmd = if let Some(mut xxd) = find_program("xxd")? {
             info!("previewing with xxd");
             if let Some(l) = self.nbytes {
@@ -87,16 +82,20 @@ impl FileView for HexView {
             error!("no usable hex viewer found");
             return Err(ViewError::Unspecified("no hex viewer".into()));
         };
-        run_command(cmd)?;
-        Ok(())
+        Ok(ViewOutput::new(vec![Block::Raw(Passthrough::Command(
+            cmd,
+            &[],
+        ))]))
     }
 }
 
 impl FileView for FileSummary {
-    fn display(&self, req: &FileRequest, options: &ViewOptions) -> Result<(), ViewError> {
-        self.meta.display(req, options)?;
-        println!("{}", styled("Initial content:", &FIELD_NAME));
-        self.preview.display(req, options)?;
-        Ok(())
+    fn render(&self, req: &FileRequest, options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        let mut output = self.meta.render(req, options)?;
+        let preview = self.preview.render(req, options)?;
+        output
+            .blocks
+            .push(Block::Section("Initial content".into(), preview.blocks));
+        Ok(output)
     }
 }
@@ -0,0 +1,203 @@
+//! User-defined external viewers from the configuration file.
+use std::process::Command;
+
+use globset::Glob;
+use log::*;
+
+use crate::config::ViewerConfig;
+use crate::interface::*;
+use crate::mime::matches_type;
+use crate::programs::find_program;
+
+/// View with a configured external command.
+struct CommandView {
+    program: String,
+    args: Vec<String>,
+    paged: bool,
+}
+
+impl FileViewer for ViewerConfig {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn make_view(&self, req: &FileRequest, mode: &Option<ViewType>) -> Option<Box<dyn FileView>> {
+        if !self.matches(req) {
+            return None;
+        }
+        let (template, paged) = match mode {
+            Some(ViewType::Meta) => (self.meta.as_ref()?, false),
+            Some(ViewType::Head) => (self.head.as_ref()?, false),
+            Some(ViewType::Full) => (self.full.as_ref()?, self.paged),
+            // default to the most complete view available
+            None => match (&self.full, &self.head, &self.meta) {
+                (Some(full), _, _) => (full, self.paged),
+                (None, Some(head), _) => (head, false),
+                (None, None, Some(meta)) => (meta, false),
+                (None, None, None) => return None,
+            },
+        };
+
+        let mut words = match shell_words::split(template) {
+            Ok(words) => words.into_iter(),
+            Err(e) => {
+                warn!("{}: invalid command {}: {}", self.name, template, e);
+                return None;
+            }
+        };
+        let program = words.next()?;
+        let args: Vec<String> = words.collect();
+        match find_program(&program) {
+            Ok(Some(_)) => Some(Box::new(CommandView {
+                program,
+                args,
+                paged,
+            })),
+            Ok(None) => {
+                debug!("{}: program {} not found", self.name, program);
+                None
+            }
+            Err(e) => {
+                warn!("{}: cannot search for {}: {}", self.name, program, e);
+                None
+            }
+        }
+    }
+}
+
+impl ViewerConfig {
+    /// Check whether the request matches the viewer's MIME type or glob.
+    fn matches(&self, req: &FileRequest) -> bool {
+        let mime_match = self
+            .mime
+            .as_ref()
+            .is_some_and(|pat| matches_type(&req.mime_type, pat));
+        let glob_match = self.glob.as_ref().is_some_and(|pat| match Glob::new(pat) {
+            Ok(glob) => req
+                .path
+                .file_name()
+                .is_some_and(|name| glob.compile_matcher().is_match(name)),
+            Err(e) => {
+                warn!("{}: invalid glob {}: {}", self.name, pat, e);
+                false
+            }
+        });
+        mime_match || glob_match
+    }
+}
+
+impl FileView for CommandView {
+    fn render(&self, req: &FileRequest, _options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        let mut cmd = find_program(&self.program)?
+            .ok_or_else(|| ViewError::from(format!("{} not found", self.program)))?;
+        fill_template(&mut cmd, &self.args, req);
+        info!("viewing with {:?}", cmd);
+        Ok(ViewOutput {
+            blocks: vec![Block::Raw(Passthrough::Command(cmd, &[]))],
+            paged: self.paged,
+        })
+    }
+}
+
+/// Add arguments to a command, replacing `{}` with the file path (or
+/// appending the path if there is no `{}`).
+fn fill_template(cmd: &mut Command, args: &[String], req: &FileRequest) {
+    let path = req.path.as_os_str();
+    let mut used = false;
+    for arg in args {
+        if arg == "{}" {
+            cmd.arg(path);
+            used = true;
+        } else if arg.contains("{}") {
+            cmd.arg(arg.replace("{}", &req.path.to_string_lossy()));
+            used = true;
+        } else {
+            cmd.arg(arg);
+        }
+    }
+    if !used {
+        cmd.arg(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir_all, set_permissions, write, Permissions};
+    use std::os::unix::fs::PermissionsExt;
+    use std::path::PathBuf;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn viewer(full: &str) -> ViewerConfig {
+        ViewerConfig {
+            name: "test".into(),
+            mime: None,
+            glob: Some("*.dat".into()),
+            meta: None,
+            head: None,
+            full: Some(full.into()),
+            paged: false,
+        }
+    }
+
+    fn request(path: &str) -> FileRequest {
+        FileRequest {
+            path: PathBuf::from(path),
+            meta: None,
+            link_target: None,
+            mime_type: "application/octet-stream".into(),
+        }
+    }
+
+    fn args(cmd: &Command) -> Vec<String> {
+        cmd.get_args()
+            .map(|a| a.to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn runs_quoted_program() {
+        let dir = TempDir::new().unwrap();
+        let bin = dir.path().join("My Viewer").join("bin");
+        create_dir_all(&bin).unwrap();
+        let prog = bin.join("view");
+        write(&prog, "#!/bin/sh\n").unwrap();
+        set_permissions(&prog, Permissions::from_mode(0o755)).unwrap();
+
+        let template = format!("\"{}\" --title 'a title' {{}}", prog.display());
+        let req = request("my file.dat");
+        let view = viewer(&template).make_view(&req, &None).unwrap();
+        let output = view.render(&req, &ViewOptions::default()).unwrap();
+        match output.blocks.as_slice() {
+            [Block::Raw(Passthrough::Command(cmd, _))] => {
+                assert_eq!(cmd.get_program(), prog.as_os_str());
+                assert_eq!(args(cmd), ["--title", "a title", "my file.dat"]);
+            }
+            _ => panic!("expected a command"),
+        }
+    }
+
+    #[test]
+    fn substitutes_path_per_argument() {
+        let req = request("my file.dat");
+        let mut cmd = Command::new("x");
+        let words = shell_words::split("--in={} -v").unwrap();
+        fill_template(&mut cmd, &words, &req);
+        assert_eq!(args(&cmd), ["--in=my file.dat", "-v"]);
+
+        let mut cmd = Command::new("x");
+        fill_template(&mut cmd, &["-v".into()], &req);
+        assert_eq!(args(&cmd), ["-v", "my file.dat"]);
+    }
+
+    #[test]
+    fn rejects_invalid_template() {
+        let req = request("file.dat");
+        assert!(viewer("cat 'unterminated").make_view(&req, &None).is_none());
+        assert!(viewer("cat")
+            .make_view(&request("file.txt"), &None)
+            .is_none());
+    }
+}
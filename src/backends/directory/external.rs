@@ -0,0 +1,410 @@
+//! Directory listing with external programs.
+use std::ffi::OsString;
+use std::process::{Command, Stdio};
+
+use colorchoice::ColorChoice;
+use log::*;
+use uu_ls::uumain;
+
+use super::{listing, tree};
+use crate::interface::*;
+use crate::programs::find_program;
+
+/// Flags for an external lister.  Options that are `None` are not supported
+/// by the program.
+struct ListerFlags {
+    program: &'static str,
+    long: &'static [&'static str],
+    all: Option<&'static [&'static str]>,
+    git_ignore: Option<&'static [&'static str]>,
+    color_always: &'static [&'static str],
+    color_never: &'static [&'static str],
+    color_auto: &'static [&'static str],
+    /// Environment variables to force color.
+    color_always_env: &'static [(&'static str, &'static str)],
+    sort_size: Option<&'static [&'static str]>,
+    sort_time: Option<&'static [&'static str]>,
+    tree: Option<&'static [&'static str]>,
+    /// Flag prefix for the tree depth, followed by the depth.
+    depth: Option<&'static str>,
+    icons: Option<&'static [&'static str]>,
+    /// Exit codes for minor problems, such as an unreadable entry, that
+    /// still produce a listing.
+    minor_exit: &'static [i32],
+}
+
+/// Supported listers, in order of preference.
+const LISTERS: &[ListerFlags] = &[
+    ListerFlags {
+        program: "eza",
+        long: &["-l"],
+        all: Some(&["-a"]),
+        git_ignore: Some(&["--git-ignore"]),
+        color_always: &["--color=always"],
+        color_never: &["--color=never"],
+        color_auto: &["--color=auto"],
+        color_always_env: &[],
+        sort_size: Some(&["--sort=size", "--reverse"]),
+        sort_time: Some(&["--sort=modified", "--reverse"]),
+        tree: Some(&["--tree"]),
+        depth: Some("--level="),
+        icons: Some(&["--icons=always"]),
+        minor_exit: &[1],
+    },
+    ListerFlags {
+        program: "lsd",
+        long: &["-l"],
+        all: Some(&["-A"]),
+        git_ignore: None,
+        color_always: &["--color=always"],
+        color_never: &["--color=never"],
+        color_auto: &["--color=auto"],
+        color_always_env: &[],
+        sort_size: Some(&["--sizesort"]),
+        sort_time: Some(&["--timesort"]),
+        tree: Some(&["--tree"]),
+        depth: Some("--depth="),
+        icons: Some(&["--icon=always"]),
+        minor_exit: &[1],
+    },
+    ListerFlags {
+        program: "exa",
+        long: &["-l"],
+        all: Some(&["-a"]),
+        git_ignore: Some(&["--git-ignore"]),
+        color_always: &["--color=always"],
+        color_never: &["--color=never"],
+        color_auto: &["--color=auto"],
+        color_always_env: &[],
+        sort_size: Some(&["--sort=size", "--reverse"]),
+        sort_time: Some(&["--sort=modified", "--reverse"]),
+        tree: Some(&["--tree"]),
+        depth: Some("--level="),
+        icons: Some(&["--icons"]),
+        minor_exit: &[1],
+    },
+    ListerFlags {
+        program: "lla",
+        long: &["-l"],
+        all: None,
+        git_ignore: None,
+        color_always: &[],
+        color_never: &["--no-color"],
+        color_auto: &[],
+        color_always_env: &[],
+        sort_size: Some(&["--sort=size"]),
+        sort_time: Some(&["--sort=date"]),
+        tree: Some(&["--tree"]),
+        depth: Some("--depth="),
+        icons: Some(&["--icons"]),
+        minor_exit: &[],
+    },
+    ListerFlags {
+        program: "ls",
+        long: &["-l"],
+        all: Some(&["-A"]),
+        git_ignore: None,
+        color_always: &["--color=always"],
+        color_never: &["--color=never"],
+        color_auto: &["--color=auto"],
+        color_always_env: &[],
+        sort_size: Some(&["-S"]),
+        sort_time: Some(&["-t"]),
+        tree: None,
+        depth: None,
+        icons: None,
+        minor_exit: &[1],
+    },
+];
+
+/// Flags for BSD (and macOS) `ls`, which does not accept GNU's `--color`.
+const BSD_LS: ListerFlags = ListerFlags {
+    program: "ls",
+    long: &["-l"],
+    all: Some(&["-A"]),
+    git_ignore: None,
+    color_always: &["-G"],
+    color_never: &[],
+    color_auto: &["-G"],
+    color_always_env: &[("CLICOLOR_FORCE", "1")],
+    sort_size: Some(&["-S"]),
+    sort_time: Some(&["-t"]),
+    tree: None,
+    depth: None,
+    icons: None,
+    minor_exit: &[1],
+};
+
+/// Flags for the built-in `uu_ls` fallback.
+const UU_LS: ListerFlags = ListerFlags {
+    program: "uu_ls",
+    long: &["-l"],
+    all: Some(&["-A"]),
+    git_ignore: None,
+    color_always: &["--color=always"],
+    color_never: &["--color=never"],
+    color_auto: &["--color=auto"],
+    color_always_env: &[],
+    sort_size: Some(&["-S"]),
+    sort_time: Some(&["-t"]),
+    tree: None,
+    depth: None,
+    icons: None,
+    minor_exit: &[1],
+};
+
+/// Directory view with an external lister.
+pub(super) struct LSView;
+
+impl FileView for LSView {
+    fn render(&self, req: &FileRequest, options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        for lister in preferred_listers(options) {
+            if let Some(cmd) = find_program(lister.program)? {
+                let lister = if lister.program == "ls" && !is_gnu_ls(&cmd) {
+                    &BSD_LS
+                } else {
+                    lister
+                };
+                return self.external_ls(req, options, lister, cmd);
+            }
+        }
+        self.fallback_ls(req, options)
+    }
+}
+
+impl LSView {
+    fn external_ls(
+        &self,
+        req: &FileRequest,
+        options: &ViewOptions,
+        lister: &ListerFlags,
+        mut cmd: Command,
+    ) -> Result<ViewOutput, ViewError> {
+        let args = match lister.args(options) {
+            Some(args) => args,
+            None => return native_view(req, options, lister),
+        };
+        info!("listing directory with {}", lister.program);
+        debug!("arguments: {:?}", args);
+        cmd.args(args);
+        if matches!(
+            options.color_choice,
+            ColorChoice::Always | ColorChoice::AlwaysAnsi
+        ) {
+            cmd.envs(lister.color_always_env.iter().copied());
+        }
+        cmd.arg(&req.path);
+        Ok(ViewOutput::new(vec![Block::Raw(Passthrough::Command(
+            cmd,
+            lister.minor_exit,
+        ))]))
+    }
+
+    fn fallback_ls(
+        &self,
+        req: &FileRequest,
+        options: &ViewOptions,
+    ) -> Result<ViewOutput, ViewError> {
+        let mut args: Vec<OsString> = vec!["ls-internal".into(), "-F".into()];
+        match UU_LS.args(options) {
+            Some(flags) => args.extend(flags.into_iter().map(OsString::from)),
+            None => return native_view(req, options, &UU_LS),
+        }
+        info!("listing directory with fallback uu_ls");
+        args.push(req.path.as_os_str().to_os_string());
+        debug!("invocation: {:?}", args);
+        let run = move || {
+            let rc = uumain(args.into_iter());
+            if rc != 0 && !UU_LS.minor_exit.contains(&rc) {
+                Err(ViewError::Unspecified(format!(
+                    "ls exited with code {}",
+                    rc
+                )))
+            } else {
+                Ok(())
+            }
+        };
+        Ok(ViewOutput::new(vec![Block::Raw(Passthrough::Internal(
+            Box::new(run),
+        ))]))
+    }
+}
+
+/// Check whether an `ls` program accepts GNU options (GNU or uutils
+/// coreutils).
+fn is_gnu_ls(cmd: &Command) -> bool {
+    let output = Command::new(cmd.get_program())
+        .arg("--version")
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+    match output {
+        Ok(out) => {
+            out.status.success() && String::from_utf8_lossy(&out.stdout).contains("coreutils")
+        }
+        Err(_) => false,
+    }
+}
+
+/// Get the listers in order, with the user's preferred listers first.
+fn preferred_listers(options: &ViewOptions) -> Vec<&'static ListerFlags> {
+    let mut listers: Vec<_> = LISTERS.iter().collect();
+    for name in &options.listers {
+        if !LISTERS.iter().any(|l| l.program == name) {
+            warn!("unsupported directory lister {}", name);
+        }
+    }
+    // stable sort keeps the default order for the rest
+    listers.sort_by_key(|l| {
+        options
+            .listers
+            .iter()
+            .position(|n| n == l.program)
+            .unwrap_or(usize::MAX)
+    });
+    listers
+}
+
+/// Fall back to our native views when a lister cannot honor the options.
+fn native_view(
+    req: &FileRequest,
+    options: &ViewOptions,
+    lister: &ListerFlags,
+) -> Result<ViewOutput, ViewError> {
+    info!(
+        "{} cannot apply requested options, using native listing",
+        lister.program
+    );
+    if options.tree {
+        tree::TreeView.render(req, options)
+    } else {
+        listing::ListView.render(req, options)
+    }
+}
+
+impl ListerFlags {
+    /// Translate view options into arguments for this lister, or `None` if it
+    /// cannot honor options that affect what is listed.  Cosmetic options it
+    /// does not support (such as icons) are skipped.
+    fn args(&self, options: &ViewOptions) -> Option<Vec<String>> {
+        let mut args: Vec<String> = Vec::new();
+        let mut push = |flags: &[&str]| args.extend(flags.iter().map(|f| f.to_string()));
+
+        if options.long_display {
+            push(self.long);
+        }
+        if options.show_hidden {
+            push(self.all?);
+        }
+        if options.hide_ignored {
+            push(self.git_ignore?);
+        }
+        push(match options.color_choice {
+            ColorChoice::Always | ColorChoice::AlwaysAnsi => self.color_always,
+            ColorChoice::Never => self.color_never,
+            ColorChoice::Auto => self.color_auto,
+        });
+        match options.sort {
+            SortKey::Name => (),
+            SortKey::Size => push(self.sort_size?),
+            SortKey::Time => push(self.sort_time?),
+            // only our native lister knows about MIME types
+            SortKey::Type => return None,
+        }
+        if options.tree {
+            push(self.tree?);
+        }
+        if options.icons {
+            match self.icons {
+                Some(flags) => push(flags),
+                None => debug!("{} does not support icons", self.program),
+            }
+        }
+        if let (true, Some(depth)) = (options.tree, tree::tree_depth(options)) {
+            args.push(format!("{}{}", self.depth?, depth));
+        }
+        Some(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lister(name: &str) -> &'static ListerFlags {
+        LISTERS.iter().find(|l| l.program == name).unwrap()
+    }
+
+    #[test]
+    fn translates_options() {
+        let options = ViewOptions {
+            long_display: true,
+            show_hidden: true,
+            color_choice: ColorChoice::Always,
+            sort: SortKey::Size,
+            ..Default::default()
+        };
+        assert_eq!(
+            lister("eza").args(&options).unwrap(),
+            ["-l", "-a", "--color=always", "--sort=size", "--reverse"]
+        );
+        assert_eq!(
+            lister("ls").args(&options).unwrap(),
+            ["-l", "-A", "--color=always", "-S"]
+        );
+        assert_eq!(BSD_LS.args(&options).unwrap(), ["-l", "-A", "-G", "-S"]);
+    }
+
+    #[test]
+    fn passes_tree_depth() {
+        let mut options = ViewOptions {
+            tree: true,
+            color_choice: ColorChoice::Never,
+            ..Default::default()
+        };
+        assert_eq!(
+            lister("lsd").args(&options).unwrap(),
+            ["--color=never", "--tree", "--depth=3"]
+        );
+        options.max_depth = Some(1);
+        assert_eq!(
+            lister("eza").args(&options).unwrap(),
+            ["--color=never", "--tree", "--level=1"]
+        );
+        options.speed = ViewSpeed::Slow;
+        options.max_depth = None;
+        assert_eq!(
+            lister("eza").args(&options).unwrap(),
+            ["--color=never", "--tree"]
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_options() {
+        let hidden = ViewOptions {
+            show_hidden: true,
+            ..Default::default()
+        };
+        assert!(lister("lla").args(&hidden).is_none());
+        let by_type = ViewOptions {
+            sort: SortKey::Type,
+            ..Default::default()
+        };
+        assert!(lister("eza").args(&by_type).is_none());
+        let tree = ViewOptions {
+            tree: true,
+            ..Default::default()
+        };
+        assert!(lister("ls").args(&tree).is_none());
+    }
+
+    #[test]
+    fn skips_unsupported_icons() {
+        let options = ViewOptions {
+            icons: true,
+            color_choice: ColorChoice::Never,
+            ..Default::default()
+        };
+        assert_eq!(lister("ls").args(&options).unwrap(), ["--color=never"]);
+    }
+}
@@ -0,0 +1,165 @@
+//! Native directory listing with detected file types.
+use std::cmp::Reverse;
+use std::ffi::OsString;
+use std::fs::Metadata;
+use std::path::Path;
+
+use anstyle::Style;
+use chrono::{DateTime, Local};
+use log::*;
+
+use super::{entry_decoration, entry_type, filtered_walker};
+use crate::fileinfo::mode_string;
+use crate::git::{DirStatus, GitStatus};
+use crate::interface::*;
+use crate::mime::mime_db;
+use crate::styling::{FILE_SIZE, FILE_TYPE};
+
+/// Directory listing view.
+pub(super) struct ListView;
+
+/// A single entry in a directory listing.
+struct Entry {
+    name: OsString,
+    meta: Option<Metadata>,
+    mime: String,
+}
+
+impl FileView for ListView {
+    fn render(&self, req: &FileRequest, options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        let mut entries = read_entries(req, options)?;
+        sort_entries(&mut entries, options.sort);
+        let git = DirStatus::for_dir(&req.path, options);
+
+        let mut columns = Vec::new();
+        if options.long_display {
+            columns.push(Column::new("Mode", Align::Left));
+            columns.push(Column::new("Size", Align::Right));
+            columns.push(Column::new("Modified", Align::Left));
+        }
+        if git.is_some() {
+            columns.push(Column::new("Git", Align::Left));
+        }
+        if options.long_display {
+            columns.push(Column::new("Type", Align::Left));
+            columns.push(Column::new("Name", Align::Left));
+        } else {
+            columns.push(Column::new("Name", Align::Left));
+            columns.push(Column::new("Type", Align::Left));
+        }
+
+        let rows = entries
+            .iter()
+            .map(|entry| {
+                let mut row = Vec::new();
+                if options.long_display {
+                    row.push(entry.mode_string().into());
+                    row.push(Value::Styled(entry.size_string(), &FILE_SIZE));
+                    row.push(entry.time_string().into());
+                }
+                if let Some(git) = &git {
+                    let status = git.get(Path::new(&entry.name));
+                    row.push(GitStatus::styled_code(status).into());
+                }
+                let name = Value::Styled(entry.display_name(), entry.name_style());
+                let mime = Value::Styled(entry.mime.clone(), &FILE_TYPE);
+                if options.long_display {
+                    row.extend([mime, name]);
+                } else {
+                    row.extend([name, mime]);
+                }
+                row
+            })
+            .collect();
+
+        let table = Table {
+            columns,
+            rows,
+            headings: false,
+        };
+        Ok(ViewOutput::new(vec![Block::Table(table)]))
+    }
+}
+
+/// Read the entries of a directory and detect their types.
+fn read_entries(req: &FileRequest, options: &ViewOptions) -> Result<Vec<Entry>, ViewError> {
+    let db = mime_db();
+    let mut entries = Vec::new();
+    let mut walker = filtered_walker(req, options);
+    walker.max_depth(Some(1));
+    for ent in walker.build() {
+        let ent = ent.map_err(ViewError::wrap)?;
+        if ent.depth() == 0 {
+            continue;
+        }
+        let name = ent.file_name().to_os_string();
+        let meta = match ent.metadata() {
+            Ok(m) => Some(m),
+            Err(e) => {
+                warn!("{}: cannot read metadata: {}", ent.path().display(), e);
+                None
+            }
+        };
+        let mime = entry_type(&db, &name, meta.as_ref());
+        entries.push(Entry { name, meta, mime });
+    }
+    debug!("read {} directory entries", entries.len());
+    Ok(entries)
+}
+
+fn sort_entries(entries: &mut [Entry], key: SortKey) {
+    entries.sort_by_cached_key(|e| e.name.to_string_lossy().to_lowercase());
+    match key {
+        SortKey::Name => (),
+        SortKey::Size => entries.sort_by_key(|e| Reverse(e.meta.as_ref().map(|m| m.len()))),
+        SortKey::Time => {
+            entries.sort_by_key(|e| Reverse(e.meta.as_ref().and_then(|m| m.modified().ok())))
+        }
+        SortKey::Type => entries.sort_by(|a, b| a.mime.cmp(&b.mime)),
+    }
+}
+
+impl Entry {
+    /// Get the entry name with an `ls -F` style type marker.
+    fn display_name(&self) -> String {
+        let marker = self.meta.as_ref().map(|m| entry_decoration(m).1);
+        format!(
+            "{}{}",
+            self.name.to_string_lossy(),
+            marker.unwrap_or_default()
+        )
+    }
+
+    fn name_style(&self) -> &'static Style {
+        static PLAIN: Style = Style::new();
+        self.meta
+            .as_ref()
+            .map(|m| entry_decoration(m).0)
+            .unwrap_or(&PLAIN)
+    }
+
+    fn size_string(&self) -> String {
+        match &self.meta {
+            Some(m) if m.is_file() => format!("{}", friendly::bytes(m.len())),
+            _ => "-".into(),
+        }
+    }
+
+    fn time_string(&self) -> String {
+        match self.meta.as_ref().and_then(|m| m.modified().ok()) {
+            Some(time) => {
+                let time: DateTime<Local> = time.into();
+                time.format("%Y-%m-%d %H:%M").to_string()
+            }
+            None => "????-??-?? ??:??".into(),
+        }
+    }
+
+    /// Format the file mode like `ls -l`.
+    fn mode_string(&self) -> String {
+        match &self.meta {
+            Some(m) => mode_string(m),
+            None => "??????????".into(),
+        }
+    }
+}
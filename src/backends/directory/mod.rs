@@ -0,0 +1,90 @@
+use std::ffi::OsStr;
+use std::fs::Metadata;
+use std::os::unix::fs::PermissionsExt;
+
+use anstyle::Style;
+use ignore::WalkBuilder;
+use shared_mime::{FileQuery, MimeDB};
+
+use crate::interface::*;
+use crate::styling::{DIRECTORY, EXECUTABLE, SYMLINK};
+
+mod external;
+mod listing;
+mod summary;
+mod tree;
+
+/// Directory backend.
+pub struct DirBackend {}
+
+/// Directory view, dispatching to the configured lister.
+struct DirView;
+
+impl FileViewer for DirBackend {
+    fn name(&self) -> &'static str {
+        "directory"
+    }
+
+    fn make_view(&self, req: &FileRequest, mode: &Option<ViewType>) -> Option<Box<dyn FileView>> {
+        if req.mime_type == "inode/directory" {
+            match mode {
+                Some(ViewType::Meta) => Some(Box::new(summary::DirMeta)),
+                _ => Some(Box::new(DirView)),
+            }
+        } else {
+            None
+        }
+    }
+}
+
+impl FileView for DirView {
+    fn render(&self, req: &FileRequest, options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        // external listers only produce text, so JSON always uses our own
+        if !options.native_ls && options.format != OutputFormat::Json {
+            external::LSView.render(req, options)
+        } else if options.tree {
+            tree::TreeView.render(req, options)
+        } else {
+            listing::ListView.render(req, options)
+        }
+    }
+}
+
+/// Set up a directory walker that applies our hidden and ignored file filters.
+fn filtered_walker(req: &FileRequest, options: &ViewOptions) -> WalkBuilder {
+    let mut walker = WalkBuilder::new(&req.path);
+    walker
+        .standard_filters(false)
+        .hidden(!options.show_hidden)
+        .parents(options.hide_ignored)
+        .git_ignore(options.hide_ignored)
+        .git_global(options.hide_ignored)
+        .git_exclude(options.hide_ignored);
+    walker
+}
+
+/// Detect the MIME type of a directory entry from its name and metadata.
+fn entry_type(db: &MimeDB, name: &OsStr, meta: Option<&Metadata>) -> String {
+    let mut query = FileQuery::builder().filename(name);
+    if let Some(meta) = meta {
+        query = query.metadata(meta.clone());
+    }
+    db.query(&query.build())
+        .ok()
+        .and_then(|ans| ans.best().map(|s| s.to_string()))
+        .unwrap_or_else(|| "application/octet-stream".into())
+}
+
+/// Get the display style and `ls -F` type marker for a directory entry.
+fn entry_decoration(meta: &Metadata) -> (&'static Style, &'static str) {
+    static PLAIN: Style = Style::new();
+    if meta.is_dir() {
+        (&DIRECTORY, "/")
+    } else if meta.is_symlink() {
+        (&SYMLINK, "@")
+    } else if meta.is_file() && meta.permissions().mode() & 0o111 != 0 {
+        (&EXECUTABLE, "*")
+    } else {
+        (&PLAIN, "")
+    }
+}
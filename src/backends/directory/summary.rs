@@ -0,0 +1,295 @@
+//! Directory metadata summaries.
+use std::collections::HashMap;
+use std::fs::read_dir;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use log::*;
+use shared_mime::FileQuery;
+use walkdir::WalkDir;
+
+use crate::interface::*;
+use crate::mime::mime_db;
+use crate::styling::EXTRA_MARKER;
+
+/// Time limit for recursive directory scans in the default view.
+const DEFAULT_SCAN_BUDGET: Duration = Duration::from_millis(500);
+/// Time limit for recursive directory scans with `--slow`.
+const SLOW_SCAN_BUDGET: Duration = Duration::from_secs(30);
+/// Number of largest files to report.
+const LARGEST_FILES: usize = 5;
+/// Number of MIME types to report without `--long`.
+const SHORT_TYPES: usize = 8;
+
+/// Directory metadata view.
+pub(super) struct DirMeta;
+
+/// Counts of a directory's immediate entries.
+#[derive(Default)]
+struct EntryCounts {
+    files: usize,
+    dirs: usize,
+    links: usize,
+    other: usize,
+    size: u64,
+}
+
+/// Results of a recursive directory scan.
+#[derive(Default)]
+struct TreeScan {
+    files: usize,
+    dirs: usize,
+    size: u64,
+    depth: usize,
+    largest: Vec<(u64, PathBuf)>,
+    types: HashMap<String, (usize, u64)>,
+    errors: usize,
+    complete: bool,
+}
+
+impl MetaView for DirMeta {
+    fn describe(&self, req: &FileRequest, options: &ViewOptions) -> Result<MetaInfo, ViewError> {
+        let mut info = MetaInfo::new("directory");
+        if options.speed == ViewSpeed::Fast {
+            let nfiles = read_dir(&req.path)?.count();
+            info.summary = Some(Field::new(
+                "entries",
+                "Entries",
+                Value::Count(nfiles as u64, "entries"),
+            ));
+            return Ok(info);
+        }
+
+        let counts = EntryCounts::scan(req)?;
+        info.summary = Some(Field::new(
+            "entries",
+            "Entries",
+            Value::Count(counts.total() as u64, "entries"),
+        ));
+        info.add("files", "Files", counts.files);
+        info.add("file_size", "File size", Value::Bytes(counts.size));
+        info.add("directories", "Directories", counts.dirs);
+        info.add("symlinks", "Symlinks", counts.links);
+        if counts.other > 0 {
+            info.add("other", "Other", counts.other);
+        }
+
+        if let Some(budget) = scan_budget(&options.speed) {
+            let scan = TreeScan::scan(req, budget);
+            scan.add_fields(&mut info, options);
+        }
+        Ok(info)
+    }
+}
+
+/// Get the time limit for a recursive scan, or `None` to skip it.
+fn scan_budget(speed: &ViewSpeed) -> Option<Duration> {
+    match speed {
+        ViewSpeed::Fast => None,
+        ViewSpeed::Default => Some(DEFAULT_SCAN_BUDGET),
+        ViewSpeed::Slow => Some(SLOW_SCAN_BUDGET),
+    }
+}
+
+impl EntryCounts {
+    fn scan(req: &FileRequest) -> Result<EntryCounts, ViewError> {
+        let mut counts = EntryCounts::default();
+        for ent in read_dir(&req.path)? {
+            let ent = ent?;
+            let ft = ent.file_type()?;
+            if ft.is_file() {
+                counts.files += 1;
+                counts.size += ent.metadata().map(|m| m.len()).unwrap_or_default();
+            } else if ft.is_dir() {
+                counts.dirs += 1;
+            } else if ft.is_symlink() {
+                counts.links += 1;
+            } else {
+                counts.other += 1;
+            }
+        }
+        Ok(counts)
+    }
+
+    fn total(&self) -> usize {
+        self.files + self.dirs + self.links + self.other
+    }
+}
+
+impl TreeScan {
+    /// Walk the directory tree, stopping if we exceed the time budget.
+    fn scan(req: &FileRequest, budget: Duration) -> TreeScan {
+        let db = mime_db();
+        let start = Instant::now();
+        let mut scan = TreeScan {
+            complete: true,
+            ..Default::default()
+        };
+
+        for ent in WalkDir::new(&req.path).min_depth(1) {
+            if start.elapsed() > budget {
+                info!("directory scan exceeded {:?}, stopping", budget);
+                scan.complete = false;
+                break;
+            }
+            let ent = match ent {
+                Ok(e) => e,
+                Err(e) => {
+                    debug!("scan error: {}", e);
+                    scan.errors += 1;
+                    continue;
+                }
+            };
+            scan.depth = scan.depth.max(ent.depth());
+            if ent.file_type().is_dir() {
+                scan.dirs += 1;
+                continue;
+            } else if !ent.file_type().is_file() {
+                continue;
+            }
+
+            let meta = match ent.metadata() {
+                Ok(m) => m,
+                Err(e) => {
+                    debug!("{}: {}", ent.path().display(), e);
+                    scan.errors += 1;
+                    continue;
+                }
+            };
+            let size = meta.len();
+            scan.files += 1;
+            scan.size += size;
+            scan.add_largest(size, ent.path().to_path_buf());
+
+            let query = FileQuery::builder()
+                .filename(ent.file_name())
+                .metadata(meta)
+                .build();
+            let mime = db
+                .query(&query)
+                .ok()
+                .and_then(|a| a.best())
+                .unwrap_or("application/octet-stream");
+            let entry = scan.types.entry(mime.to_string()).or_default();
+            entry.0 += 1;
+            entry.1 += size;
+        }
+        debug!("scanned directory in {:?}", start.elapsed());
+        scan
+    }
+
+    fn add_largest(&mut self, size: u64, path: PathBuf) {
+        if self.largest.len() < LARGEST_FILES || size > self.largest[LARGEST_FILES - 1].0 {
+            self.largest.push((size, path));
+            self.largest
+                .sort_by_key(|(size, _)| std::cmp::Reverse(*size));
+            self.largest.truncate(LARGEST_FILES);
+        }
+    }
+
+    fn add_fields(&self, info: &mut MetaInfo, options: &ViewOptions) {
+        info.add("total_size", "Total size", Value::Bytes(self.size));
+        info.add("total_files", "Total files", self.files);
+        if !self.complete {
+            let note = match options.speed {
+                ViewSpeed::Slow => "partial, scan timed out",
+                _ => "partial, scan timed out (use --slow to scan longer)",
+            };
+            info.add("scan", "Scan", Value::Styled(note.into(), &EXTRA_MARKER));
+        }
+        info.add("subdirectories", "Subdirectories", self.dirs);
+        info.add("depth", "Depth", self.depth);
+        if self.errors > 0 {
+            info.add("unreadable_entries", "Unreadable entries", self.errors);
+        }
+
+        if !self.largest.is_empty() {
+            let largest = self
+                .largest
+                .iter()
+                .map(|(size, path)| {
+                    Value::Text(format!("{} ({})", path.display(), friendly::bytes(*size)))
+                })
+                .collect();
+            info.add("largest_files", "Largest files", Value::List(largest));
+        }
+
+        let mut types: Vec<_> = self.types.iter().collect();
+        types.sort_by(|a, b| b.1 .1.cmp(&a.1 .1).then_with(|| a.0.cmp(b.0)));
+        if types.is_empty() {
+            return;
+        }
+        let shown = if options.long_display {
+            types.len()
+        } else {
+            SHORT_TYPES
+        };
+        let mut list: Vec<Value> = types
+            .iter()
+            .take(shown)
+            .map(|(mime, (count, size))| {
+                Value::Text(format!(
+                    "{}: {} files ({})",
+                    mime,
+                    friendly::integer(*count),
+                    friendly::bytes(*size)
+                ))
+            })
+            .collect();
+        if types.len() > shown {
+            list.push(Value::Styled(
+                format!("… {} more types", types.len() - shown),
+                &EXTRA_MARKER,
+            ));
+        }
+        info.add("types", "Types", Value::List(list));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir, write};
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn request(dir: &TempDir) -> FileRequest {
+        FileRequest {
+            path: dir.path().to_path_buf(),
+            meta: None,
+            link_target: None,
+            mime_type: "inode/directory".into(),
+        }
+    }
+
+    #[test]
+    fn budget_follows_speed() {
+        assert_eq!(scan_budget(&ViewSpeed::Fast), None);
+        assert!(scan_budget(&ViewSpeed::Default).unwrap() < Duration::from_secs(1));
+        assert_eq!(scan_budget(&ViewSpeed::Slow), Some(SLOW_SCAN_BUDGET));
+    }
+
+    #[test]
+    fn scans_tree() {
+        let dir = TempDir::new().unwrap();
+        create_dir(dir.path().join("sub")).unwrap();
+        write(dir.path().join("sub").join("a.txt"), "hello").unwrap();
+        write(dir.path().join("b.txt"), "hi").unwrap();
+        let scan = TreeScan::scan(&request(&dir), SLOW_SCAN_BUDGET);
+        assert!(scan.complete);
+        assert_eq!((scan.files, scan.dirs, scan.size, scan.depth), (2, 1, 7, 2));
+        assert_eq!(scan.types["text/plain"], (2, 7));
+    }
+
+    #[test]
+    fn marks_partial_scan() {
+        let dir = TempDir::new().unwrap();
+        write(dir.path().join("a.txt"), "hello").unwrap();
+        let scan = TreeScan::scan(&request(&dir), Duration::ZERO);
+        assert!(!scan.complete);
+        let mut info = MetaInfo::new("directory");
+        scan.add_fields(&mut info, &ViewOptions::default());
+        assert!(info.fields.iter().any(|f| f.label == "Scan"));
+    }
+}
@@ -0,0 +1,258 @@
+//! Tree view for directories.
+use std::cmp::Reverse;
+use std::ffi::OsString;
+use std::fs::Metadata;
+use std::path::Path;
+
+use log::*;
+
+use super::{entry_decoration, entry_type, filtered_walker};
+use crate::git::{DirStatus, GitStatus};
+use crate::interface::*;
+use crate::mime::mime_db;
+use crate::styling::{styled, DIRECTORY, EXTRA_MARKER, FILE_SIZE};
+
+/// Number of entries to show in each directory without `--long`.
+const MAX_CHILDREN: usize = 25;
+/// Tree depth when none is given, unless we are allowed to be slow.
+const DEFAULT_DEPTH: usize = 3;
+
+/// Directory tree view.
+pub(super) struct TreeView;
+
+/// A node in the directory tree.
+struct Node {
+    name: OsString,
+    meta: Option<Metadata>,
+    /// File size, or total size of a directory's contents.
+    size: u64,
+    /// Whether all of a directory's contents were scanned.
+    complete: bool,
+    children: Vec<Node>,
+}
+
+impl FileView for TreeView {
+    fn render(&self, req: &FileRequest, options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        let mut root = build_tree(req, options);
+        root.sort(options.sort);
+
+        let mut head = styled(req.path.display().to_string(), &DIRECTORY).to_string();
+        if root.complete {
+            let size = styled(format!("{}", friendly::bytes(root.size)), &FILE_SIZE);
+            head.push_str(&format!(" ({})", size));
+        }
+        let mut lines = vec![head];
+        let git = DirStatus::for_dir(&req.path, options);
+        render_node(
+            &mut lines,
+            &root,
+            Path::new(""),
+            "",
+            1,
+            options,
+            git.as_ref(),
+        );
+        Ok(ViewOutput::new(vec![Block::Text(lines)]))
+    }
+}
+
+/// Get the depth of tree to display.  Without a depth limit, trees are only
+/// unbounded with `--slow`.
+pub(super) fn tree_depth(options: &ViewOptions) -> Option<usize> {
+    match (options.max_depth, &options.speed) {
+        (Some(depth), _) => Some(depth),
+        (None, ViewSpeed::Slow) => None,
+        (None, _) => Some(DEFAULT_DEPTH),
+    }
+}
+
+/// Walk the directory to build the tree, applying hidden and ignored filters.
+///
+/// The walk is limited to the display depth, unless we are allowed to be slow,
+/// in which case we walk everything to compute complete directory sizes.
+fn build_tree(req: &FileRequest, options: &ViewOptions) -> Node {
+    let walk_depth = if options.speed == ViewSpeed::Slow {
+        None
+    } else {
+        tree_depth(options)
+    };
+    let mut walker = filtered_walker(req, options);
+    walker
+        .max_depth(walk_depth)
+        .sort_by_file_name(|a, b| a.cmp(b));
+
+    let mut root = Node::new(req.path.as_os_str().to_os_string(), req.meta.clone());
+    root.complete = walk_depth != Some(0);
+    // the stack holds the current directory and its ancestors
+    let mut stack = vec![root];
+    for ent in walker.build() {
+        let ent = match ent {
+            Ok(e) => e,
+            Err(e) => {
+                warn!("error walking directory: {}", e);
+                continue;
+            }
+        };
+        let depth = ent.depth();
+        if depth == 0 {
+            continue;
+        }
+        while stack.len() > depth {
+            let node = stack.pop().expect("non-empty stack");
+            stack.last_mut().expect("root").add_child(node);
+        }
+
+        let mut node = Node::new(ent.file_name().to_os_string(), ent.metadata().ok());
+        if node.is_dir() {
+            node.complete = walk_depth != Some(depth);
+            stack.push(node);
+        } else {
+            stack.last_mut().expect("root").add_child(node);
+        }
+    }
+    while stack.len() > 1 {
+        let node = stack.pop().expect("non-empty stack");
+        stack.last_mut().expect("root").add_child(node);
+    }
+    stack.pop().expect("root")
+}
+
+/// Render the children of a node into lines.
+fn render_node(
+    lines: &mut Vec<String>,
+    node: &Node,
+    path: &Path,
+    prefix: &str,
+    depth: usize,
+    options: &ViewOptions,
+    git: Option<&DirStatus>,
+) {
+    let shown = if options.long_display {
+        node.children.len()
+    } else {
+        node.children.len().min(MAX_CHILDREN)
+    };
+    let hidden = node.children.len() - shown;
+
+    for (i, child) in node.children.iter().take(shown).enumerate() {
+        let last = i + 1 == shown && hidden == 0;
+        let child_path = path.join(&child.name);
+        let (style, marker) = match &child.meta {
+            Some(m) => entry_decoration(m),
+            None => (&EXTRA_MARKER, ""),
+        };
+        let mut line = format!("{}{}", prefix, if last { "└── " } else { "├── " });
+        if let Some(status) = git.and_then(|g| g.get(&child_path)) {
+            line.push_str(&format!("{} ", GitStatus::styled_code(Some(status))));
+        }
+        line.push_str(&format!(
+            "{}{}",
+            styled(child.name.to_string_lossy(), style),
+            marker
+        ));
+        if let Some(size) = child.size_label() {
+            line.push_str(&format!(" ({})", styled(size, &FILE_SIZE)));
+        }
+        lines.push(line);
+
+        if child.is_dir() && tree_depth(options).is_none_or(|d| depth < d) {
+            let prefix = format!("{}{}", prefix, if last { "    " } else { "│   " });
+            render_node(lines, child, &child_path, &prefix, depth + 1, options, git);
+        }
+    }
+
+    if hidden > 0 {
+        lines.push(format!(
+            "{}└── {}",
+            prefix,
+            styled(format!("… {} more entries", hidden), &EXTRA_MARKER)
+        ));
+    }
+}
+
+impl Node {
+    fn new(name: OsString, meta: Option<Metadata>) -> Node {
+        let size = match &meta {
+            Some(m) if m.is_file() => m.len(),
+            _ => 0,
+        };
+        Node {
+            name,
+            meta,
+            size,
+            complete: true,
+            children: Vec::new(),
+        }
+    }
+
+    fn is_dir(&self) -> bool {
+        self.meta.as_ref().is_some_and(|m| m.is_dir())
+    }
+
+    fn add_child(&mut self, child: Node) {
+        self.size += child.size;
+        self.complete &= child.complete;
+        self.children.push(child);
+    }
+
+    /// Get the size to display, if it is known.
+    fn size_label(&self) -> Option<String> {
+        let meta = self.meta.as_ref()?;
+        if meta.is_file() || (meta.is_dir() && self.complete) {
+            Some(format!("{}", friendly::bytes(self.size)))
+        } else {
+            None
+        }
+    }
+
+    /// Sort the tree.  Entries are already in name order from the walk, and
+    /// the sorts are stable, so ties stay in name order.
+    fn sort(&mut self, key: SortKey) {
+        match key {
+            SortKey::Name => (),
+            SortKey::Size => self.children.sort_by_key(|n| Reverse(n.size)),
+            SortKey::Time => self
+                .children
+                .sort_by_key(|n| Reverse(n.meta.as_ref().and_then(|m| m.modified().ok()))),
+            SortKey::Type => {
+                let db = mime_db();
+                self.children
+                    .sort_by_cached_key(|n| entry_type(&db, &n.name, n.meta.as_ref()))
+            }
+        }
+        for child in &mut self.children {
+            child.sort(key);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn sorts_by_type() {
+        let dir = TempDir::new().unwrap();
+        for name in ["a.txt", "b.png", "c.txt", "d.html"] {
+            write(dir.path().join(name), "").unwrap();
+        }
+        let req = FileRequest {
+            path: dir.path().to_path_buf(),
+            meta: dir.path().metadata().ok(),
+            link_target: None,
+            mime_type: "inode/directory".into(),
+        };
+        let mut root = build_tree(&req, &ViewOptions::default());
+        root.sort(SortKey::Type);
+        let names: Vec<_> = root
+            .children
+            .iter()
+            .map(|n| n.name.to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["b.png", "d.html", "a.txt", "c.txt"]);
+    }
+}
@@ -0,0 +1,321 @@
+//! Font files (TrueType, OpenType, WOFF and WOFF2).
+use std::collections::BTreeSet;
+use std::fs::read;
+
+use log::*;
+use ttf_parser::{name_id, Face, Language, Tag};
+
+use crate::interface::*;
+use crate::mime::mime_db;
+use crate::styling::{styled, EXTRA_MARKER, FIELD_NAME, MISSING};
+
+mod blocks;
+mod woff;
+
+/// Legacy MIME types for fonts outside the `font/` tree.
+const FONT_TYPES: &[&str] = &[
+    "application/x-font-ttf",
+    "application/x-font-otf",
+    "application/vnd.ms-opentype",
+    "application/font-woff",
+];
+
+/// Number of Unicode blocks to list without `--long`.
+const SHORT_BLOCKS: usize = 8;
+
+/// Sample strings for showing coverage of different scripts.
+const SAMPLES: &[(&str, &str)] = &[
+    ("Latin", "The quick brown fox jumps over the lazy dog"),
+    ("Digits", "0123456789 !?&@#$%*()[]{}"),
+    ("Latin accents", "Àéîõü çñ ßø æœ ĀĞİŁŐŠŽ"),
+    ("Greek", "Ξεσκεπάζω την ψυχοφθόρα βδελυγμία"),
+    ("Cyrillic", "Съешь же ещё этих мягких французских булок"),
+    ("Hebrew", "דג סקרן שט בים מאוכזב"),
+    ("Arabic", "نص حكيم له سر قاطع وذو شأن عظيم"),
+    ("Devanagari", "ऋषियों को सताने वाले दुष्ट राक्षसों"),
+    ("Thai", "เป็นมนุษย์สุดประเสริฐเลิศคุณค่า"),
+    ("Chinese", "天地玄黄宇宙洪荒"),
+    ("Japanese", "いろはにほへと ちりぬるを"),
+    ("Korean", "다람쥐 헌 쳇바퀴에 타고파"),
+    ("Symbols", "←↑→↓ ∀∂∈∑√∞ ★☺♠♥"),
+];
+
+/// Font backend.
+pub struct FontBackend;
+
+struct FontMeta;
+struct FontSample {
+    all: bool,
+}
+
+/// A font file loaded into SFNT form.
+struct FontData {
+    format: &'static str,
+    data: Vec<u8>,
+    faces: u32,
+}
+
+impl FileViewer for FontBackend {
+    fn name(&self) -> &'static str {
+        "font"
+    }
+
+    fn make_view(&self, req: &FileRequest, mode: &Option<ViewType>) -> Option<Box<dyn FileView>> {
+        let db = mime_db();
+        if !req.mime_type.starts_with("font/")
+            && !FONT_TYPES.iter().any(|t| db.is_subtype(&req.mime_type, t))
+        {
+            return None;
+        }
+        match mode {
+            None | Some(ViewType::Meta) => Some(Box::new(FontMeta)),
+            Some(ViewType::Head) => Some(Box::new(FontSample { all: false })),
+            Some(ViewType::Full) => Some(Box::new(FontSample { all: true })),
+        }
+    }
+}
+
+impl MetaView for FontMeta {
+    fn describe(&self, req: &FileRequest, options: &ViewOptions) -> Result<MetaInfo, ViewError> {
+        let db = mime_db();
+        let font = load_font(req)?;
+        let face = Face::parse(&font.data, 0).map_err(ViewError::wrap)?;
+
+        let mut info = MetaInfo::new(db.description(&req.mime_type).unwrap_or("Font file"));
+        info.size = req.file_size();
+
+        info.add("format", "Format", font.format);
+        if font.faces > 1 {
+            info.add(
+                "faces",
+                "Faces",
+                format!("{} (showing the first)", font.faces),
+            );
+        }
+        let family = find_name(&face, name_id::TYPOGRAPHIC_FAMILY)
+            .or_else(|| find_name(&face, name_id::FAMILY));
+        let style = find_name(&face, name_id::TYPOGRAPHIC_SUBFAMILY)
+            .or_else(|| find_name(&face, name_id::SUBFAMILY));
+        let mut names = vec![
+            ("family", "Family", family),
+            ("style", "Style", style),
+            ("version", "Version", find_name(&face, name_id::VERSION)),
+        ];
+        if options.long_display {
+            names.extend([
+                (
+                    "full_name",
+                    "Full name",
+                    find_name(&face, name_id::FULL_NAME),
+                ),
+                (
+                    "postscript_name",
+                    "PostScript name",
+                    find_name(&face, name_id::POST_SCRIPT_NAME),
+                ),
+                ("designer", "Designer", find_name(&face, name_id::DESIGNER)),
+                (
+                    "manufacturer",
+                    "Manufacturer",
+                    find_name(&face, name_id::MANUFACTURER),
+                ),
+                (
+                    "copyright",
+                    "Copyright",
+                    find_name(&face, name_id::COPYRIGHT_NOTICE),
+                ),
+                ("license", "License", find_name(&face, name_id::LICENSE)),
+            ]);
+        }
+        for (key, label, value) in names {
+            info.add_opt(key, label, value);
+        }
+
+        let mut traits = vec![
+            format!("weight {}", face.weight().to_number()),
+            format!("{:?}", face.width()).to_lowercase(),
+            format!("{:?}", face.style()).to_lowercase(),
+        ];
+        if face.is_monospaced() {
+            traits.push("monospaced".into());
+        }
+        info.add("traits", "Traits", traits.join(", "));
+        info.add(
+            "glyphs",
+            "Glyphs",
+            Value::Count(face.number_of_glyphs().into(), "glyphs"),
+        );
+        info.add("units_per_em", "Units per em", face.units_per_em());
+
+        if face.is_variable() {
+            let axes = face
+                .variation_axes()
+                .into_iter()
+                .map(|axis| {
+                    let name = find_name(&face, axis.name_id)
+                        .map(|n| format!(" ({})", n))
+                        .unwrap_or_default();
+                    Value::Text(format!(
+                        "{}{}: {}–{}, default {}",
+                        axis.tag, name, axis.min_value, axis.max_value, axis.def_value
+                    ))
+                })
+                .collect();
+            info.add("variation_axes", "Variation axes", Value::List(axes));
+        }
+
+        let cps = codepoints(&face);
+        let (blocks, other) = blocks::block_coverage(&cps);
+        info.add(
+            "characters",
+            "Characters",
+            Value::Count(cps.len() as u64, "characters"),
+        );
+        let shown = if options.long_display {
+            blocks.len()
+        } else {
+            SHORT_BLOCKS
+        };
+        let mut coverage: Vec<Value> = blocks
+            .iter()
+            .take(shown)
+            .map(|(name, count, size)| Value::Text(format!("{}: {}/{}", name, count, size)))
+            .collect();
+        if blocks.len() > shown {
+            coverage.push(Value::Styled(
+                format!("… {} more blocks", blocks.len() - shown),
+                &EXTRA_MARKER,
+            ));
+        }
+        if other > 0 && options.long_display {
+            coverage.push(Value::Text(format!("other: {}", other)));
+        }
+        info.add("unicode_blocks", "Unicode blocks", Value::List(coverage));
+
+        let features = layout_features(&face);
+        if !features.is_empty() {
+            let list: Vec<_> = features
+                .iter()
+                .map(|t| t.to_string().trim().to_string())
+                .collect();
+            info.add("opentype_features", "OpenType features", list.join(" "));
+        }
+        Ok(info)
+    }
+}
+
+impl FileView for FontSample {
+    fn render(&self, req: &FileRequest, _options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        let font = load_font(req)?;
+        let face = Face::parse(&font.data, 0).map_err(ViewError::wrap)?;
+
+        let mut rows = Vec::new();
+        for (label, text) in SAMPLES {
+            let chars: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+            let covered = chars
+                .iter()
+                .filter(|c| face.glyph_index(**c).is_some())
+                .count();
+            // only show scripts the font knows something about
+            if covered == 0 && !self.all {
+                continue;
+            }
+
+            let mut line = String::new();
+            for c in text.chars() {
+                if c.is_whitespace() || face.glyph_index(c).is_some() {
+                    line.push(c);
+                } else {
+                    line.push_str(&styled(c.to_string(), &MISSING).to_string());
+                }
+            }
+            rows.push(vec![
+                Value::Styled(label.to_string(), &FIELD_NAME),
+                Value::Text(line),
+                Value::Styled(format!("{}/{}", covered, chars.len()), &EXTRA_MARKER),
+            ]);
+        }
+        let table = Table {
+            columns: vec![
+                Column::new("Script", Align::Right),
+                Column::new("Sample", Align::Left),
+                Column::new("Coverage", Align::Right),
+            ],
+            rows,
+            headings: false,
+        };
+        Ok(ViewOutput::new(vec![Block::Table(table)]))
+    }
+}
+
+/// Load a font file, unwrapping web fonts.
+fn load_font(req: &FileRequest) -> Result<FontData, ViewError> {
+    let data = read(&req.path)?;
+    let (format, data) = match data.get(0..4) {
+        Some(b"wOFF") => ("WOFF", woff::decode_woff(&data)?),
+        Some(b"wOF2") => ("WOFF2", woff::decode_woff2(&data)?),
+        Some(b"OTTO") => ("OpenType (CFF outlines)", data),
+        Some(b"ttcf") => ("TrueType/OpenType collection", data),
+        Some([0, 1, 0, 0]) | Some(b"true") => ("TrueType", data),
+        _ => return Err("unrecognized font format".into()),
+    };
+    let faces = ttf_parser::fonts_in_collection(&data).unwrap_or(1);
+    debug!("loaded {} font with {} faces", format, faces);
+    Ok(FontData {
+        format,
+        data,
+        faces,
+    })
+}
+
+/// Look up a name, preferring US English entries.
+fn find_name(face: &Face, id: u16) -> Option<String> {
+    let mut found = None;
+    for name in face.names() {
+        if name.name_id != id {
+            continue;
+        }
+        if let Some(text) = name.to_string() {
+            if name.language() == Language::English_UnitedStates {
+                return Some(text);
+            }
+            found.get_or_insert(text);
+        }
+    }
+    found
+}
+
+/// Get the sorted Unicode code points mapped by the font.
+fn codepoints(face: &Face) -> Vec<u32> {
+    let mut cps = BTreeSet::new();
+    if let Some(cmap) = face.tables().cmap {
+        for table in cmap.subtables {
+            if table.is_unicode() {
+                table.codepoints(|c| {
+                    cps.insert(c);
+                });
+            }
+        }
+    }
+    cps.into_iter().collect()
+}
+
+/// Get the OpenType layout feature tags from the GSUB and GPOS tables.
+fn layout_features(face: &Face) -> BTreeSet<Tag> {
+    let tables = face.tables();
+    tables
+        .gsub
+        .iter()
+        .chain(tables.gpos.iter())
+        .flat_map(|t| t.features)
+        .map(|f| f.tag)
+        .collect()
+}
+
+fn be_u16(data: &[u8], off: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(off..off + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], off: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(off..off + 4)?.try_into().ok()?))
+}
@@ -0,0 +1,238 @@
+//! WOFF and WOFF2 web font unwrapping.
+//!
+//! Both formats are converted back into a plain SFNT (TrueType/OpenType)
+//! file so they can be parsed like any other font.  WOFF2 fonts usually
+//! store `glyf` and `loca` in a transformed encoding; we do not reconstruct
+//! those tables, as nothing we report depends on glyph outlines.
+use std::io::Read;
+
+use brotli_decompressor::Decompressor;
+use flate2::read::ZlibDecoder;
+use log::*;
+
+use super::{be_u16, be_u32};
+use crate::interface::ViewError;
+
+/// Tags for the WOFF2 known-table index.
+const KNOWN_TAGS: [&[u8; 4]; 63] = [
+    b"cmap", b"head", b"hhea", b"hmtx", b"maxp", b"name", b"OS/2", b"post", b"cvt ", b"fpgm",
+    b"glyf", b"loca", b"prep", b"CFF ", b"VORG", b"EBDT", b"EBLC", b"gasp", b"hdmx", b"kern",
+    b"LTSH", b"PCLT", b"VDMX", b"vhea", b"vmtx", b"BASE", b"GDEF", b"GPOS", b"GSUB", b"EBSC",
+    b"JSTF", b"MATH", b"CBDT", b"CBLC", b"COLR", b"CPAL", b"SVG ", b"sbix", b"acnt", b"avar",
+    b"bdat", b"bloc", b"bsln", b"cvar", b"fdsc", b"feat", b"fmtx", b"fvar", b"gvar", b"hsty",
+    b"just", b"lcar", b"mort", b"morx", b"opbd", b"prop", b"trak", b"Zapf", b"Silf", b"Glat",
+    b"Gloc", b"Feat", b"Sill",
+];
+
+/// Largest table size we are willing to decompress.
+const MAX_TABLE: u32 = 64 * 1024 * 1024;
+
+/// A font table extracted from a web font.
+struct Table {
+    tag: [u8; 4],
+    data: Vec<u8>,
+}
+
+/// Unwrap a WOFF (version 1) font.
+pub(super) fn decode_woff(data: &[u8]) -> Result<Vec<u8>, ViewError> {
+    let flavor = be_u32(data, 4).ok_or("truncated WOFF header")?;
+    let count = be_u16(data, 12).ok_or("truncated WOFF header")? as usize;
+    let mut tables = Vec::with_capacity(count);
+    for i in 0..count {
+        let off = 44 + i * 20;
+        let entry = data.get(off..off + 20).ok_or("truncated WOFF directory")?;
+        let tag: [u8; 4] = entry[0..4].try_into().expect("slice length");
+        let start = be_u32(entry, 4).unwrap_or_default() as usize;
+        let comp_len = be_u32(entry, 8).unwrap_or_default() as usize;
+        let orig_len = be_u32(entry, 12).unwrap_or_default();
+        if orig_len > MAX_TABLE {
+            return Err("WOFF table too large".into());
+        }
+        let body = data
+            .get(start..start + comp_len)
+            .ok_or("WOFF table out of bounds")?;
+        let data = if comp_len < orig_len as usize {
+            let mut out = Vec::with_capacity(orig_len as usize);
+            ZlibDecoder::new(body)
+                .take(orig_len as u64)
+                .read_to_end(&mut out)?;
+            out
+        } else {
+            body.to_vec()
+        };
+        tables.push(Table { tag, data });
+    }
+    Ok(build_sfnt(flavor, tables))
+}
+
+/// Unwrap a WOFF2 font, skipping transformed tables.
+pub(super) fn decode_woff2(data: &[u8]) -> Result<Vec<u8>, ViewError> {
+    let flavor = be_u32(data, 4).ok_or("truncated WOFF2 header")?;
+    if &flavor.to_be_bytes() == b"ttcf" {
+        return Err("WOFF2 font collections are not supported".into());
+    }
+    let count = be_u16(data, 12).ok_or("truncated WOFF2 header")? as usize;
+    let comp_len = be_u32(data, 20).ok_or("truncated WOFF2 header")? as usize;
+
+    // table directory: (tag, stored length, transformed?)
+    let mut pos = 48;
+    let mut entries = Vec::with_capacity(count);
+    for _ in 0..count {
+        let flags = *data.get(pos).ok_or("truncated WOFF2 directory")?;
+        pos += 1;
+        let tag: [u8; 4] = match flags & 0x3F {
+            63 => {
+                let tag = data.get(pos..pos + 4).ok_or("truncated WOFF2 directory")?;
+                pos += 4;
+                tag.try_into().expect("slice length")
+            }
+            i => *KNOWN_TAGS[i as usize],
+        };
+        let version = flags >> 6;
+        // glyf and loca use version 3 for the null transform, others use 0
+        let transformed = if &tag == b"glyf" || &tag == b"loca" {
+            version != 3
+        } else {
+            version != 0
+        };
+        let mut length = read_base128(data, &mut pos)?;
+        if transformed {
+            length = read_base128(data, &mut pos)?;
+        }
+        if length > MAX_TABLE {
+            return Err("WOFF2 table too large".into());
+        }
+        entries.push((tag, length as usize, transformed));
+    }
+
+    // the directory can list many tables, so limit their total size too
+    let total: usize = entries.iter().map(|(_, len, _)| len).sum();
+    if total > MAX_TABLE as usize {
+        return Err("WOFF2 font too large".into());
+    }
+    let compressed = data
+        .get(pos..pos + comp_len)
+        .ok_or("WOFF2 data out of bounds")?;
+    let mut stream = Vec::with_capacity(total);
+    Decompressor::new(compressed, 4096)
+        .take(total as u64)
+        .read_to_end(&mut stream)?;
+    if stream.len() != total {
+        return Err("truncated WOFF2 font data".into());
+    }
+
+    let mut tables = Vec::with_capacity(count);
+    let mut off = 0;
+    for (tag, len, transformed) in entries {
+        if transformed {
+            debug!(
+                "skipping transformed {} table",
+                String::from_utf8_lossy(&tag)
+            );
+        } else {
+            tables.push(Table {
+                tag,
+                data: stream[off..off + len].to_vec(),
+            });
+        }
+        off += len;
+    }
+    Ok(build_sfnt(flavor, tables))
+}
+
+/// Read a WOFF2 `UIntBase128` value.
+fn read_base128(data: &[u8], pos: &mut usize) -> Result<u32, ViewError> {
+    let mut value: u32 = 0;
+    for i in 0..5 {
+        let b = *data.get(*pos).ok_or("truncated WOFF2 directory")?;
+        *pos += 1;
+        if (i == 0 && b == 0x80) || value & 0xFE00_0000 != 0 {
+            return Err("invalid WOFF2 integer".into());
+        }
+        value = (value << 7) | (b & 0x7F) as u32;
+        if b & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    Err("invalid WOFF2 integer".into())
+}
+
+/// Assemble tables into an SFNT file.  Checksums are not computed, since
+/// the result is only used for parsing.
+fn build_sfnt(flavor: u32, mut tables: Vec<Table>) -> Vec<u8> {
+    tables.sort_by_key(|t| t.tag);
+    let count = tables.len() as u16;
+    let mut out = Vec::new();
+    out.extend_from_slice(&flavor.to_be_bytes());
+    out.extend_from_slice(&count.to_be_bytes());
+    // search range, entry selector and range shift are unused by parsers
+    out.extend_from_slice(&[0; 6]);
+
+    let mut offset = 12 + 16 * tables.len();
+    for table in &tables {
+        out.extend_from_slice(&table.tag);
+        out.extend_from_slice(&[0; 4]);
+        out.extend_from_slice(&(offset as u32).to_be_bytes());
+        out.extend_from_slice(&(table.data.len() as u32).to_be_bytes());
+        offset += table.data.len().next_multiple_of(4);
+    }
+    for table in &tables {
+        out.extend_from_slice(&table.data);
+        out.resize(out.len().next_multiple_of(4), 0);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Build a WOFF2 header and directory with the given table lengths.
+    fn woff2(lengths: &[u32]) -> Vec<u8> {
+        let mut data = vec![0; 48];
+        data[0..4].copy_from_slice(b"wOF2");
+        data[4..8].copy_from_slice(&0x0001_0000u32.to_be_bytes());
+        data[12..14].copy_from_slice(&(lengths.len() as u16).to_be_bytes());
+        for len in lengths {
+            // known tag "name", null transform
+            data.push(5);
+            let mut groups = Vec::new();
+            let mut n = *len;
+            loop {
+                groups.push((n & 0x7F) as u8);
+                n >>= 7;
+                if n == 0 {
+                    break;
+                }
+            }
+            for (i, b) in groups.iter().enumerate().rev() {
+                data.push(if i > 0 { b | 0x80 } else { *b });
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn rejects_oversized_fonts() {
+        let lengths = vec![MAX_TABLE; 1024];
+        let err = decode_woff2(&woff2(&lengths)).err().unwrap();
+        assert!(err.to_string().ends_with("WOFF2 font too large"));
+        assert!(decode_woff2(&woff2(&[MAX_TABLE + 1])).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_fonts() {
+        assert!(decode_woff2(b"wOF2").is_err());
+        // a table with no compressed data
+        assert!(decode_woff2(&woff2(&[16])).is_err());
+    }
+
+    #[test]
+    fn reads_base128() {
+        let mut pos = 0;
+        assert_eq!(read_base128(&[0x81, 0x00], &mut pos).unwrap(), 128);
+        assert_eq!(pos, 2);
+        assert!(read_base128(&[0x80, 0x01], &mut 0).is_err());
+        assert!(read_base128(&[0xFF; 6], &mut 0).is_err());
+    }
+}
@@ -0,0 +1,163 @@
+//! HTML documents rendered as text.
+use std::fs::read;
+
+use anstyle::Style;
+use html2text::render::RichAnnotation;
+use log::*;
+
+use super::text::{TextView, HEAD_LINES};
+use crate::interface::*;
+use crate::mime::mime_db;
+use crate::styling::{color_enabled, combine_styles, styled, term_width, CODE, EXTRA_MARKER, LINK};
+
+/// HTML backend.
+pub struct HtmlBackend;
+
+struct HtmlMeta;
+struct HtmlView {
+    lines: Option<usize>,
+}
+
+impl FileViewer for HtmlBackend {
+    fn name(&self) -> &'static str {
+        "html"
+    }
+
+    fn make_view(&self, req: &FileRequest, mode: &Option<ViewType>) -> Option<Box<dyn FileView>> {
+        let db = mime_db();
+        if db.is_subtype(&req.mime_type, "text/html") {
+            match mode {
+                Some(ViewType::Meta) => Some(Box::new(HtmlMeta)),
+                Some(ViewType::Head) => Some(Box::new(HtmlView {
+                    lines: Some(HEAD_LINES),
+                })),
+                _ => Some(Box::new(HtmlView { lines: None })),
+            }
+        } else {
+            None
+        }
+    }
+}
+
+impl MetaView for HtmlMeta {
+    fn describe(&self, req: &FileRequest, _options: &ViewOptions) -> Result<MetaInfo, ViewError> {
+        let db = mime_db();
+        let bytes = read(&req.path)?;
+        let text = String::from_utf8_lossy(&bytes);
+        let dom = tl::parse(&text, tl::ParserOptions::default()).map_err(ViewError::wrap)?;
+        let summary = HtmlSummary::scan(&dom);
+
+        let mut info = MetaInfo::new(db.description(&req.mime_type).unwrap_or("HTML document"));
+        info.size = req.file_size();
+        info.add_opt("title", "Title", summary.title);
+        info.add_opt("charset", "Charset", summary.charset);
+        info.add_opt("language", "Language", summary.lang);
+        info.add("links", "Links", summary.links);
+        info.add("images", "Images", summary.images);
+        info.add("scripts", "Scripts", summary.scripts);
+        Ok(info)
+    }
+}
+
+impl FileView for HtmlView {
+    fn render(&self, req: &FileRequest, options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        if options.raw_source {
+            info!("showing raw HTML source");
+            let source = TextView { lines: self.lines };
+            return source.render(req, options);
+        }
+
+        let bytes = read(&req.path)?;
+        let width = term_width();
+        let text = if color_enabled() {
+            html2text::config::rich().link_footnotes(true).coloured(
+                &bytes[..],
+                width,
+                style_annotated,
+            )
+        } else {
+            html2text::config::plain()
+                .link_footnotes(true)
+                .string_from_read(&bytes[..], width)
+        };
+        let text = text.map_err(ViewError::wrap)?;
+
+        let lines = text.lines().take(self.lines.unwrap_or(usize::MAX));
+        Ok(ViewOutput {
+            blocks: vec![Block::Text(lines.map(|l| l.to_string()).collect())],
+            paged: self.lines.is_none(),
+        })
+    }
+}
+
+/// Style a span of rendered HTML text.
+fn style_annotated(annotations: &[RichAnnotation], text: &str) -> String {
+    let style = annotations.iter().fold(Style::new(), |acc, ann| {
+        let style = match ann {
+            RichAnnotation::Link(_) => LINK,
+            RichAnnotation::Image(_) => EXTRA_MARKER,
+            RichAnnotation::Emphasis => Style::new().italic(),
+            RichAnnotation::Strong => Style::new().bold(),
+            RichAnnotation::Strikeout => Style::new().strikethrough(),
+            RichAnnotation::Code | RichAnnotation::Preformat(_) => CODE,
+            _ => Style::new(),
+        };
+        combine_styles(&acc, &style)
+    });
+    styled(text, &style).to_string()
+}
+
+/// Summary information about an HTML document.
+#[derive(Default)]
+struct HtmlSummary {
+    title: Option<String>,
+    charset: Option<String>,
+    lang: Option<String>,
+    links: usize,
+    images: usize,
+    scripts: usize,
+}
+
+impl HtmlSummary {
+    fn scan(dom: &tl::VDom) -> HtmlSummary {
+        let parser = dom.parser();
+        let mut summary = HtmlSummary::default();
+        for node in dom.nodes() {
+            let tag = match node.as_tag() {
+                Some(t) => t,
+                None => continue,
+            };
+            let attrs = tag.attributes();
+            let attr = |name: &str| {
+                attrs
+                    .get(name)
+                    .flatten()
+                    .map(|v| v.as_utf8_str().trim().to_string())
+            };
+            match tag.name().as_utf8_str().to_ascii_lowercase().as_str() {
+                "html" => summary.lang = attr("lang"),
+                "title" if summary.title.is_none() => {
+                    let title = tag.inner_text(parser);
+                    let title = title.split_whitespace().collect::<Vec<_>>().join(" ");
+                    summary.title = Some(title);
+                }
+                "meta" if summary.charset.is_none() => {
+                    summary.charset = attr("charset").or_else(|| {
+                        let equiv = attr("http-equiv")?;
+                        if !equiv.eq_ignore_ascii_case("content-type") {
+                            return None;
+                        }
+                        let content = attr("content")?;
+                        let (_, cs) = content.split_once("charset=")?;
+                        Some(cs.trim_matches(['"', '\'', ' ', ';']).to_string())
+                    });
+                }
+                "a" if attrs.contains("href") => summary.links += 1,
+                "img" => summary.images += 1,
+                "script" => summary.scripts += 1,
+                _ => (),
+            }
+        }
+        summary
+    }
+}
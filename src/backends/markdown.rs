@@ -0,0 +1,437 @@
+//! Rendered Markdown documents.
+use std::fs::read_to_string;
+use std::mem::take;
+
+use anstyle::{AnsiColor, Color, Style};
+use log::*;
+use pulldown_cmark::{Alignment, CodeBlockKind, Event, LinkType, Options, Parser, Tag, TagEnd};
+use textwrap::core::display_width;
+
+use super::text::{TextView, HEAD_LINES};
+use crate::highlight::{highlight, SyntaxHint};
+use crate::interface::*;
+use crate::mime::mime_db;
+use crate::styling::{combine_styles, styled, term_width, CODE, EXTRA_MARKER, FIELD_NAME, LINK};
+
+static HEADING: Style = Style::new()
+    .bold()
+    .fg_color(Some(Color::Ansi(AnsiColor::Blue)));
+
+/// Markdown backend.
+pub struct MarkdownBackend;
+
+struct MarkdownView {
+    lines: Option<usize>,
+}
+
+impl FileViewer for MarkdownBackend {
+    fn name(&self) -> &'static str {
+        "markdown"
+    }
+
+    fn make_view(&self, req: &FileRequest, mode: &Option<ViewType>) -> Option<Box<dyn FileView>> {
+        let db = mime_db();
+        if db.is_subtype(&req.mime_type, "text/markdown") {
+            match mode {
+                // the text backend provides the metadata
+                Some(ViewType::Meta) => None,
+                Some(ViewType::Head) => Some(Box::new(MarkdownView {
+                    lines: Some(HEAD_LINES),
+                })),
+                _ => Some(Box::new(MarkdownView { lines: None })),
+            }
+        } else {
+            None
+        }
+    }
+}
+
+impl FileView for MarkdownView {
+    fn render(&self, req: &FileRequest, options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        if options.raw_source {
+            info!("showing raw markdown source");
+            let source = TextView { lines: self.lines };
+            return source.render(req, options);
+        }
+
+        let text = read_to_string(&req.path)?;
+        let mut lines = render_markdown(&text, term_width(), options.theme.as_deref());
+        if let Some(n) = self.lines {
+            lines.truncate(n);
+        }
+        Ok(ViewOutput {
+            blocks: vec![Block::Text(lines)],
+            paged: self.lines.is_none(),
+        })
+    }
+}
+
+/// Render Markdown text into lines wrapped to the specified width.
+fn render_markdown(text: &str, width: usize, theme: Option<&str>) -> Vec<String> {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_FOOTNOTES;
+    let mut renderer = Renderer::new(width, theme);
+    for event in Parser::new_ext(text, options) {
+        renderer.event(event);
+    }
+    renderer.finish()
+}
+
+/// Line prefix for a nested block (block quote or list item).
+struct Prefix {
+    first: String,
+    rest: String,
+    used: bool,
+}
+
+struct TableState {
+    aligns: Vec<Alignment>,
+    rows: Vec<Vec<String>>,
+    row: Vec<String>,
+}
+
+struct Renderer {
+    width: usize,
+    theme: Option<String>,
+    lines: Vec<String>,
+    inline: String,
+    styles: Vec<Style>,
+    prefixes: Vec<Prefix>,
+    lists: Vec<Option<u64>>,
+    links: Vec<(LinkType, String)>,
+    code: Option<(String, String)>,
+    table: Option<TableState>,
+    blank_at: Option<usize>,
+}
+
+impl Renderer {
+    fn new(width: usize, theme: Option<&str>) -> Renderer {
+        Renderer {
+            width: width.max(20),
+            theme: theme.map(|t| t.to_string()),
+            lines: Vec::new(),
+            inline: String::new(),
+            styles: Vec::new(),
+            prefixes: Vec::new(),
+            lists: Vec::new(),
+            links: Vec::new(),
+            code: None,
+            table: None,
+            blank_at: None,
+        }
+    }
+
+    fn finish(mut self) -> Vec<String> {
+        self.flush();
+        while self.lines.last().is_some_and(|l| l.trim().is_empty()) {
+            self.lines.pop();
+        }
+        self.lines
+    }
+
+    fn event(&mut self, event: Event) {
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => {
+                if let Some((_, code)) = self.code.as_mut() {
+                    code.push_str(&text);
+                } else {
+                    self.push_text(&text);
+                }
+            }
+            Event::Code(text) => {
+                let style = self.current_style();
+                let style = combine_styles(&style, &CODE);
+                self.inline.push_str(&styled(&text, &style).to_string());
+            }
+            Event::InlineMath(text) | Event::DisplayMath(text) => self.push_text(&text),
+            Event::Html(text) | Event::InlineHtml(text) => {
+                self.inline
+                    .push_str(&styled(text.trim_end(), &EXTRA_MARKER).to_string());
+            }
+            Event::FootnoteReference(name) => self.push_text(&format!("[^{}]", name)),
+            Event::SoftBreak => self.inline.push(' '),
+            Event::HardBreak => self.inline.push('\n'),
+            Event::Rule => {
+                self.flush();
+                let (first, _) = self.indents();
+                let width = self.width.saturating_sub(display_width(&first));
+                let rule = styled("─".repeat(width), &EXTRA_MARKER);
+                self.lines.push(format!("{}{}", first, rule));
+                self.blank();
+            }
+            Event::TaskListMarker(checked) => {
+                self.push_text(if checked { "[x] " } else { "[ ] " });
+            }
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Heading { level, .. } => {
+                self.flush();
+                let mut style = HEADING;
+                if level == pulldown_cmark::HeadingLevel::H1 {
+                    style = style.underline();
+                }
+                self.styles.push(style);
+            }
+            Tag::BlockQuote(_) => {
+                self.flush();
+                let bar = styled("│ ", &EXTRA_MARKER).to_string();
+                self.prefixes.push(Prefix {
+                    first: bar.clone(),
+                    rest: bar,
+                    used: false,
+                });
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush();
+                let lang = match kind {
+                    CodeBlockKind::Fenced(info) => info
+                        .split_whitespace()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                    CodeBlockKind::Indented => String::new(),
+                };
+                self.code = Some((lang, String::new()));
+            }
+            Tag::List(start) => {
+                self.flush();
+                self.lists.push(start);
+            }
+            Tag::Item => {
+                self.flush();
+                let bullet = match self.lists.last_mut() {
+                    Some(Some(n)) => {
+                        *n += 1;
+                        format!("{}. ", *n - 1)
+                    }
+                    _ => "• ".to_string(),
+                };
+                let rest = " ".repeat(display_width(&bullet));
+                self.prefixes.push(Prefix {
+                    first: bullet,
+                    rest,
+                    used: false,
+                });
+            }
+            Tag::FootnoteDefinition(name) => {
+                self.flush();
+                self.push_text(&format!("[^{}]: ", name));
+            }
+            Tag::Table(aligns) => {
+                self.flush();
+                self.table = Some(TableState {
+                    aligns,
+                    rows: Vec::new(),
+                    row: Vec::new(),
+                });
+            }
+            Tag::Emphasis => self.styles.push(Style::new().italic()),
+            Tag::Strong => self.styles.push(Style::new().bold()),
+            Tag::Strikethrough => self.styles.push(Style::new().strikethrough()),
+            Tag::Link {
+                link_type,
+                dest_url,
+                ..
+            } => {
+                self.styles.push(LINK);
+                self.links.push((link_type, dest_url.to_string()));
+            }
+            Tag::Image { .. } => {
+                self.styles.push(EXTRA_MARKER);
+                self.push_text("[image: ");
+            }
+            _ => (),
+        }
+    }
+
+    fn end(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::HtmlBlock | TagEnd::FootnoteDefinition => {
+                self.flush();
+                self.blank();
+            }
+            TagEnd::Heading(_) => {
+                self.styles.pop();
+                self.flush();
+                self.blank();
+            }
+            TagEnd::BlockQuote(_) => {
+                self.flush();
+                if self.blank_at.is_some_and(|i| i + 1 == self.lines.len()) {
+                    self.lines.pop();
+                }
+                self.prefixes.pop();
+                self.blank();
+            }
+            TagEnd::CodeBlock => {
+                if let Some((lang, code)) = self.code.take() {
+                    let lines = self.highlight(&lang, &code);
+                    for line in lines {
+                        let (first, _) = self.indents();
+                        self.lines.push(format!("{}    {}", first, line));
+                    }
+                }
+                self.blank();
+            }
+            TagEnd::List(_) => {
+                self.flush();
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.blank();
+                }
+            }
+            TagEnd::Item => {
+                self.flush();
+                self.prefixes.pop();
+            }
+            TagEnd::TableCell => {
+                let cell = take(&mut self.inline);
+                if let Some(table) = self.table.as_mut() {
+                    table.row.push(cell.trim().to_string());
+                }
+            }
+            TagEnd::TableHead | TagEnd::TableRow => {
+                if let Some(table) = self.table.as_mut() {
+                    let row = take(&mut table.row);
+                    table.rows.push(row);
+                }
+            }
+            TagEnd::Table => {
+                if let Some(table) = self.table.take() {
+                    self.render_table(table);
+                }
+                self.blank();
+            }
+            TagEnd::Emphasis | TagEnd::Strong | TagEnd::Strikethrough => {
+                self.styles.pop();
+            }
+            TagEnd::Link => {
+                self.styles.pop();
+                if let Some((link_type, url)) = self.links.pop() {
+                    if !matches!(link_type, LinkType::Autolink | LinkType::Email) {
+                        let url = styled(format!("<{}>", url), &EXTRA_MARKER);
+                        self.inline.push_str(&format!(" {}", url));
+                    }
+                }
+            }
+            TagEnd::Image => {
+                self.push_text("]");
+                self.styles.pop();
+            }
+            _ => (),
+        }
+    }
+
+    fn current_style(&self) -> Style {
+        self.styles
+            .iter()
+            .fold(Style::new(), |acc, style| combine_styles(&acc, style))
+    }
+
+    fn push_text(&mut self, text: &str) {
+        let style = self.current_style();
+        self.inline.push_str(&styled(text, &style).to_string());
+    }
+
+    /// Get the first-line and continuation indents for the next block.
+    fn indents(&mut self) -> (String, String) {
+        let mut first = String::new();
+        let mut rest = String::new();
+        for prefix in self.prefixes.iter_mut() {
+            first.push_str(if prefix.used {
+                &prefix.rest
+            } else {
+                &prefix.first
+            });
+            rest.push_str(&prefix.rest);
+            prefix.used = true;
+        }
+        (first, rest)
+    }
+
+    /// Wrap and emit the pending inline text.
+    fn flush(&mut self) {
+        let text = take(&mut self.inline);
+        let text = text.trim();
+        if text.is_empty() {
+            return;
+        }
+        let (first, rest) = self.indents();
+        let options = textwrap::Options::new(self.width)
+            .initial_indent(&first)
+            .subsequent_indent(&rest)
+            .break_words(false);
+        for line in textwrap::wrap(text, options) {
+            self.lines.push(line.into_owned());
+        }
+    }
+
+    /// Emit a blank line, if the previous line is not already blank.
+    fn blank(&mut self) {
+        let last = self.lines.len().checked_sub(1);
+        if last.is_some() && last != self.blank_at {
+            let rest: String = self.prefixes.iter().map(|p| p.rest.as_str()).collect();
+            self.blank_at = Some(self.lines.len());
+            self.lines.push(rest.trim_end().to_string());
+        }
+    }
+
+    fn highlight(&self, lang: &str, code: &str) -> Vec<String> {
+        highlight(code, SyntaxHint::Token(lang), self.theme.as_deref())
+    }
+
+    fn render_table(&mut self, table: TableState) {
+        let ncols = table.rows.iter().map(|r| r.len()).max().unwrap_or_default();
+        let mut widths = vec![0; ncols];
+        for row in &table.rows {
+            for (i, cell) in row.iter().enumerate() {
+                widths[i] = widths[i].max(display_width(cell));
+            }
+        }
+
+        let sep = styled(" │ ", &EXTRA_MARKER).to_string();
+        for (ri, row) in table.rows.iter().enumerate() {
+            let mut cells = Vec::with_capacity(ncols);
+            for (i, width) in widths.iter().enumerate() {
+                let cell = row.get(i).map(|c| c.as_str()).unwrap_or_default();
+                let pad = width - display_width(cell);
+                let cell = if ri == 0 {
+                    styled(cell, &FIELD_NAME).to_string()
+                } else {
+                    cell.to_string()
+                };
+                let cell = match table.aligns.get(i) {
+                    Some(Alignment::Right) => format!("{}{}", " ".repeat(pad), cell),
+                    Some(Alignment::Center) => format!(
+                        "{}{}{}",
+                        " ".repeat(pad / 2),
+                        cell,
+                        " ".repeat(pad - pad / 2)
+                    ),
+                    _ => format!("{}{}", cell, " ".repeat(pad)),
+                };
+                cells.push(cell);
+            }
+            let (first, _) = self.indents();
+            self.lines.push(
+                format!("{}{}", first, cells.join(&sep))
+                    .trim_end()
+                    .to_string(),
+            );
+
+            if ri == 0 {
+                let rule: Vec<String> = widths.iter().map(|w| "─".repeat(*w)).collect();
+                let rule = styled(rule.join("─┼─"), &EXTRA_MARKER);
+                let (first, _) = self.indents();
+                self.lines.push(format!("{}{}", first, rule));
+            }
+        }
+    }
+}
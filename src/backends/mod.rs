@@ -1,18 +1,64 @@
-use crate::interface::FileViewer;
+use std::iter::{once, once_with};
 
+use crate::interface::{FileViewer, ViewError, ViewOptions};
+
+mod audio;
 mod binfile;
+mod custom;
 mod directory;
+mod font;
+mod html;
 mod image;
+mod markdown;
+mod odf;
+mod plugin;
+mod special;
+mod symlink;
 mod text;
+mod video;
+
+/// A backend that can be shared across threads.
+pub type Backend = dyn FileViewer + Send + Sync;
 
-static BUILTIN_BACKENDS: &'static [&(dyn FileViewer + Send + Sync)] = &[
+static BUILTIN_BACKENDS: &[&Backend] = &[
+    &symlink::SymlinkBackend,
     &directory::DirBackend {},
+    &special::SpecialBackend,
     &image::ImageBackend,
+    &audio::AudioBackend,
+    &video::VideoBackend,
+    &font::FontBackend,
+    &odf::OdfBackend,
+    &markdown::MarkdownBackend,
+    &html::HtmlBackend,
     &text::TextBackend {},
     &binfile::BinfileBackend {},
 ];
 
-/// Get the registered backends.
-pub fn backends() -> Vec<&'static (dyn FileViewer + Send + Sync)> {
-    Vec::from(BUILTIN_BACKENDS)
+/// Get the registered backends: the user's external viewers, then plugins,
+/// followed by the built-in backends.  Plugins are only discovered if the
+/// iteration reaches them.
+pub fn backends(options: &ViewOptions) -> impl Iterator<Item = &Backend> {
+    let viewers = options.viewers.iter().map(|v| v as &Backend);
+    let plugins = once_with(plugin::plugins).flatten().map(|p| p as &Backend);
+    viewers
+        .chain(plugins)
+        .chain(BUILTIN_BACKENDS.iter().copied())
+}
+
+/// Get the backends to try for a request, restricted to the backend named
+/// in the options if there is one.
+pub fn selected_backends(
+    options: &ViewOptions,
+) -> Result<Box<dyn Iterator<Item = &Backend> + '_>, ViewError> {
+    match &options.backend {
+        None => Ok(Box::new(backends(options))),
+        Some(name) => match backends(options).find(|b| b.name() == name) {
+            Some(back) => Ok(Box::new(once(back))),
+            None => {
+                let names: Vec<_> = backends(options).map(|b| b.name()).collect();
+                Err(format!("unknown backend {} (available: {})", name, names.join(", ")).into())
+            }
+        },
+    }
 }
@@ -0,0 +1,515 @@
+//! OpenDocument (ODT, ODS, ODP) files.
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+
+use log::*;
+use quick_xml::events::{BytesStart, Event};
+use quick_xml::Reader;
+use zip::ZipArchive;
+
+use super::text::HEAD_LINES;
+use crate::mime::mime_db;
+use crate::{
+    interface::*,
+    styling::{styled, EXTRA_MARKER, FIELD_NAME},
+};
+
+const ODF_PREFIX: &str = "application/vnd.oasis.opendocument.";
+const MAX_CELL_WIDTH: usize = 40;
+/// Limit for expanding repeated empty rows and cells (spreadsheets often
+/// repeat empty cells out to the maximum sheet size).
+const MAX_EMPTY_REPEAT: usize = 64;
+/// Limit for expanding other repeated rows and cells.
+const MAX_REPEAT: usize = 1024;
+/// Limit for the number of cells read in a row.
+const MAX_COLUMNS: usize = 256;
+
+/// OpenDocument backend.
+pub struct OdfBackend;
+
+struct OdfMeta;
+struct OdfText {
+    lines: Option<usize>,
+}
+struct OdfTable {
+    rows: Option<usize>,
+}
+
+/// A block of text extracted from a document.
+enum TextBlock {
+    Heading(String),
+    Para(String),
+    Item(usize, String),
+    Slide(usize, Option<String>),
+}
+
+impl FileViewer for OdfBackend {
+    fn name(&self) -> &'static str {
+        "odf"
+    }
+
+    fn make_view(&self, req: &FileRequest, mode: &Option<ViewType>) -> Option<Box<dyn FileView>> {
+        let kind = req.mime_type.strip_prefix(ODF_PREFIX)?;
+        let lines = match mode {
+            Some(ViewType::Meta) => return Some(Box::new(OdfMeta)),
+            Some(ViewType::Head) => Some(HEAD_LINES),
+            _ => None,
+        };
+        if kind.starts_with("spreadsheet") {
+            Some(Box::new(OdfTable { rows: lines }))
+        } else if kind.starts_with("text") || kind.starts_with("presentation") {
+            Some(Box::new(OdfText { lines }))
+        } else {
+            None
+        }
+    }
+}
+
+impl MetaView for OdfMeta {
+    fn describe(&self, req: &FileRequest, _options: &ViewOptions) -> Result<MetaInfo, ViewError> {
+        let db = mime_db();
+        let mut zip = open_zip(req)?;
+        let mut info = MetaInfo::new(
+            db.description(&req.mime_type)
+                .unwrap_or("OpenDocument file"),
+        );
+        info.size = req.file_size();
+
+        let meta = match zip.by_name("meta.xml") {
+            Ok(f) => read_meta(BufReader::new(f))?,
+            Err(e) => {
+                warn!("cannot read document metadata: {}", e);
+                return Ok(info);
+            }
+        };
+        for (key, label, value) in meta.fields {
+            info.add(key, label, value);
+        }
+        for (key, label, count) in meta.stats {
+            info.add(key, label, count);
+        }
+        Ok(info)
+    }
+}
+
+impl FileView for OdfText {
+    fn render(&self, req: &FileRequest, _options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        let mut zip = open_zip(req)?;
+        let content = zip.by_name("content.xml").map_err(ViewError::wrap)?;
+        let blocks = read_text(BufReader::new(content))?;
+
+        let mut lines = Vec::new();
+        for block in blocks {
+            let text = match block {
+                TextBlock::Heading(text) => format!("{}", styled(text, &FIELD_NAME)),
+                TextBlock::Para(text) => text,
+                TextBlock::Item(depth, text) => {
+                    format!("{}- {}", "  ".repeat(depth.saturating_sub(1)), text)
+                }
+                TextBlock::Slide(n, Some(name)) => {
+                    format!("{}", styled(format!("Slide {}: {}", n, name), &FIELD_NAME))
+                }
+                TextBlock::Slide(n, None) => {
+                    format!("{}", styled(format!("Slide {}", n), &FIELD_NAME))
+                }
+            };
+            lines.extend(text.lines().map(|l| l.to_string()));
+            if self.lines.is_some_and(|n| lines.len() >= n) {
+                break;
+            }
+        }
+        if let Some(n) = self.lines {
+            lines.truncate(n);
+        }
+
+        Ok(ViewOutput {
+            blocks: vec![Block::Text(lines)],
+            paged: self.lines.is_none(),
+        })
+    }
+}
+
+impl FileView for OdfTable {
+    fn render(&self, req: &FileRequest, _options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        let mut zip = open_zip(req)?;
+        let content = zip.by_name("content.xml").map_err(ViewError::wrap)?;
+        let Sheet { name, mut rows } = match read_first_table(BufReader::new(content))? {
+            Some(s) => s,
+            None => {
+                let note = styled("spreadsheet has no sheets", &EXTRA_MARKER);
+                return Ok(ViewOutput::new(vec![Block::Text(vec![note.to_string()])]));
+            }
+        };
+
+        let mut blocks = Vec::new();
+        if let Some(name) = name {
+            blocks.push(Block::Fields(vec![Field::new("sheet", "Sheet", name)]));
+        }
+        let total = rows.len();
+        if let Some(n) = self.rows {
+            rows.truncate(n);
+        }
+        let shown = rows.len();
+        blocks.push(Block::Table(make_table(rows)));
+        if shown < total {
+            let note = styled(format!("… {} more rows", total - shown), &EXTRA_MARKER);
+            blocks.push(Block::Text(vec![note.to_string()]));
+        }
+
+        Ok(ViewOutput {
+            blocks,
+            paged: self.rows.is_none(),
+        })
+    }
+}
+
+fn open_zip(req: &FileRequest) -> Result<ZipArchive<File>, ViewError> {
+    let file = File::open(&req.path)?;
+    ZipArchive::new(file).map_err(ViewError::wrap)
+}
+
+/// Document metadata from `meta.xml`.
+#[derive(Default)]
+struct OdfMetadata {
+    fields: Vec<(&'static str, &'static str, String)>,
+    stats: Vec<(&'static str, &'static str, u64)>,
+}
+
+/// Metadata elements, with their field keys and labels.
+const META_FIELDS: &[(&[u8], &str, &str)] = &[
+    (b"dc:title", "title", "Title"),
+    (b"dc:subject", "subject", "Subject"),
+    (b"meta:initial-creator", "author", "Author"),
+    (b"dc:creator", "last_modified_by", "Last modified by"),
+    (b"meta:creation-date", "created", "Created"),
+    (b"dc:date", "modified", "Modified"),
+    (b"meta:generator", "generator", "Generator"),
+];
+
+/// Document statistics attributes, with their field keys and labels.
+const META_STATS: &[(&[u8], &str, &str)] = &[
+    (b"meta:page-count", "pages", "Pages"),
+    (b"meta:table-count", "tables", "Tables"),
+    (b"meta:image-count", "images", "Images"),
+    (b"meta:object-count", "objects", "Objects"),
+    (b"meta:paragraph-count", "paragraphs", "Paragraphs"),
+    (b"meta:word-count", "words", "Words"),
+    (b"meta:character-count", "characters", "Characters"),
+    (b"meta:cell-count", "cells", "Cells"),
+];
+
+fn read_meta<R: BufRead>(src: R) -> Result<OdfMetadata, ViewError> {
+    let mut reader = Reader::from_reader(src);
+    let mut buf = Vec::new();
+    let mut meta = OdfMetadata::default();
+    let mut field: Option<(&'static str, &'static str)> = None;
+
+    loop {
+        match reader.read_event_into(&mut buf).map_err(ViewError::wrap)? {
+            Event::Start(e) => {
+                field = META_FIELDS
+                    .iter()
+                    .find(|(tag, _, _)| e.name().as_ref() == *tag)
+                    .map(|(_, key, label)| (*key, *label));
+            }
+            Event::Empty(e) if e.name().as_ref() == b"meta:document-statistic" => {
+                for (attr, key, label) in META_STATS {
+                    if let Some(n) = attr_value(&e, attr).and_then(|v| v.parse().ok()) {
+                        meta.stats.push((key, label, n));
+                    }
+                }
+            }
+            Event::Text(t) => {
+                if let Some((key, label)) = field.take() {
+                    let text = t.unescape().map_err(ViewError::wrap)?;
+                    meta.fields.push((key, label, text.trim().to_string()));
+                }
+            }
+            Event::End(_) => field = None,
+            Event::Eof => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+
+    Ok(meta)
+}
+
+/// Extract the text blocks from a text or presentation document.
+fn read_text<R: BufRead>(src: R) -> Result<Vec<TextBlock>, ViewError> {
+    let mut reader = Reader::from_reader(src);
+    let mut buf = Vec::new();
+    let mut blocks = Vec::new();
+    // paragraphs can nest (e.g. in frames), so we keep a stack
+    let mut paras: Vec<String> = Vec::new();
+    let mut list_depth = 0;
+    let mut item_start = false;
+    let mut skip = 0;
+    let mut slides = 0;
+
+    loop {
+        let evt = reader.read_event_into(&mut buf).map_err(ViewError::wrap)?;
+        if skip > 0 {
+            match evt {
+                Event::Start(_) => skip += 1,
+                Event::End(_) => skip -= 1,
+                Event::Eof => break,
+                _ => (),
+            }
+            buf.clear();
+            continue;
+        }
+        match evt {
+            Event::Start(e) => match e.name().as_ref() {
+                b"office:annotation" | b"text:tracked-changes" | b"presentation:notes" => skip = 1,
+                b"text:p" | b"text:h" => paras.push(String::new()),
+                b"text:list" => list_depth += 1,
+                b"text:list-item" => item_start = true,
+                b"draw:page" => {
+                    slides += 1;
+                    blocks.push(TextBlock::Slide(slides, attr_value(&e, b"draw:name")));
+                }
+                _ => (),
+            },
+            Event::Empty(e) => {
+                if let Some(para) = paras.last_mut() {
+                    match e.name().as_ref() {
+                        b"text:s" => {
+                            let n = attr_value(&e, b"text:c")
+                                .and_then(|c| c.parse().ok())
+                                .unwrap_or(1);
+                            para.push_str(&" ".repeat(n));
+                        }
+                        b"text:tab" => para.push('\t'),
+                        b"text:line-break" => para.push('\n'),
+                        _ => (),
+                    }
+                }
+            }
+            Event::Text(t) => {
+                if let Some(para) = paras.last_mut() {
+                    para.push_str(&t.unescape().map_err(ViewError::wrap)?);
+                }
+            }
+            Event::End(e) => match e.name().as_ref() {
+                b"text:h" => {
+                    let text = paras.pop().unwrap_or_default();
+                    blocks.push(TextBlock::Heading(text));
+                }
+                b"text:p" => {
+                    let text = paras.pop().unwrap_or_default();
+                    if item_start {
+                        item_start = false;
+                        blocks.push(TextBlock::Item(list_depth, text));
+                    } else if list_depth > 0 {
+                        let indent = "  ".repeat(list_depth);
+                        blocks.push(TextBlock::Para(format!("{}{}", indent, text)));
+                    } else {
+                        blocks.push(TextBlock::Para(text));
+                    }
+                }
+                b"text:list" => list_depth -= 1,
+                _ => (),
+            },
+            Event::Eof => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+
+    Ok(blocks)
+}
+
+/// A sheet extracted from a spreadsheet.
+struct Sheet {
+    name: Option<String>,
+    rows: Vec<Vec<String>>,
+}
+
+/// Extract the first table from a spreadsheet.
+fn read_first_table<R: BufRead>(src: R) -> Result<Option<Sheet>, ViewError> {
+    let mut reader = Reader::from_reader(src);
+    let mut buf = Vec::new();
+    let mut name = None;
+    let mut in_table = false;
+    let mut rows: Vec<Vec<String>> = Vec::new();
+    let mut row: Vec<String> = Vec::new();
+    let mut row_repeat = 1;
+    let mut cell: Option<String> = None;
+    let mut cell_repeat = 1;
+
+    loop {
+        match reader.read_event_into(&mut buf).map_err(ViewError::wrap)? {
+            Event::Start(e) if !in_table && e.name().as_ref() == b"table:table" => {
+                in_table = true;
+                name = attr_value(&e, b"table:name");
+            }
+            Event::Start(e) if in_table => match e.name().as_ref() {
+                b"table:table-row" => {
+                    row.clear();
+                    row_repeat = repeat_count(&e, b"table:number-rows-repeated");
+                }
+                b"table:table-cell" | b"table:covered-table-cell" => {
+                    cell = Some(String::new());
+                    cell_repeat = repeat_count(&e, b"table:number-columns-repeated");
+                }
+                b"text:p" => {
+                    if let Some(c) = cell.as_mut() {
+                        if !c.is_empty() {
+                            c.push(' ');
+                        }
+                    }
+                }
+                _ => (),
+            },
+            Event::Empty(e) if in_table => match e.name().as_ref() {
+                b"table:table-cell" | b"table:covered-table-cell" => {
+                    let n = repeat_count(&e, b"table:number-columns-repeated");
+                    push_repeated(&mut row, String::new(), n);
+                    row.truncate(MAX_COLUMNS);
+                }
+                b"text:s" => {
+                    if let Some(c) = cell.as_mut() {
+                        c.push(' ');
+                    }
+                }
+                _ => (),
+            },
+            Event::Text(t) if in_table => {
+                if let Some(c) = cell.as_mut() {
+                    c.push_str(&t.unescape().map_err(ViewError::wrap)?);
+                }
+            }
+            Event::End(e) if in_table => match e.name().as_ref() {
+                b"table:table-cell" | b"table:covered-table-cell" => {
+                    if let Some(c) = cell.take() {
+                        push_repeated(&mut row, c, cell_repeat);
+                        row.truncate(MAX_COLUMNS);
+                    }
+                }
+                b"table:table-row" => {
+                    while row.last().is_some_and(|c| c.is_empty()) {
+                        row.pop();
+                    }
+                    push_repeated(&mut rows, row.clone(), row_repeat);
+                }
+                b"table:table" => break,
+                _ => (),
+            },
+            Event::Eof => break,
+            _ => (),
+        }
+        buf.clear();
+    }
+
+    if !in_table {
+        return Ok(None);
+    }
+    while rows.last().is_some_and(|r| r.is_empty()) {
+        rows.pop();
+    }
+    Ok(Some(Sheet { name, rows }))
+}
+
+/// Push a repeated row or cell, clamping the repeat count.
+fn push_repeated<T: Clone + Default + PartialEq>(vec: &mut Vec<T>, item: T, n: usize) {
+    let n = if item == T::default() {
+        n.min(MAX_EMPTY_REPEAT)
+    } else {
+        n.min(MAX_REPEAT)
+    };
+    for _ in 0..n {
+        vec.push(item.clone());
+    }
+}
+
+fn repeat_count(e: &BytesStart, attr: &[u8]) -> usize {
+    attr_value(e, attr)
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(1)
+}
+
+fn attr_value(e: &BytesStart, name: &[u8]) -> Option<String> {
+    e.attributes()
+        .flatten()
+        .find(|a| a.key.as_ref() == name)
+        .and_then(|a| a.unescape_value().ok())
+        .map(|v| v.into_owned())
+}
+
+/// Convert sheet rows to a table, using the first row as column headings.
+fn make_table(rows: Vec<Vec<String>>) -> Table {
+    let ncols = rows.iter().map(|r| r.len()).max().unwrap_or_default();
+    let cell = |row: &[String], i: usize| {
+        let text = row.get(i).map(|c| c.as_str()).unwrap_or_default();
+        truncate(text, MAX_CELL_WIDTH)
+    };
+    let mut rows = rows.iter();
+    let columns = match rows.next() {
+        Some(head) => (0..ncols)
+            .map(|i| Column::new(cell(head, i), Align::Left))
+            .collect(),
+        None => Vec::new(),
+    };
+    Table {
+        columns,
+        rows: rows
+            .map(|row| (0..ncols).map(|i| cell(row, i).into()).collect())
+            .collect(),
+        headings: true,
+    }
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    if text.chars().count() > width {
+        let mut s: String = text.chars().take(width - 1).collect();
+        s.push('…');
+        s
+    } else {
+        text.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sheet(body: &str) -> Sheet {
+        let xml = format!(
+            r#"<office:document-content><table:table table:name="S">{}</table:table></office:document-content>"#,
+            body
+        );
+        read_first_table(xml.as_bytes()).unwrap().unwrap()
+    }
+
+    #[test]
+    fn reads_cells() {
+        let s = sheet(
+            "<table:table-row><table:table-cell><text:p>a</text:p></table:table-cell>\
+             <table:table-cell><text:p>b</text:p></table:table-cell></table:table-row>",
+        );
+        assert_eq!(s.name.as_deref(), Some("S"));
+        assert_eq!(s.rows, vec![vec!["a".to_string(), "b".to_string()]]);
+    }
+
+    #[test]
+    fn clamps_repeated_cells() {
+        let s = sheet(
+            r#"<table:table-row table:number-rows-repeated="1000000">
+               <table:table-cell table:number-columns-repeated="1000000"><text:p>x</text:p></table:table-cell>
+               </table:table-row>"#,
+        );
+        assert_eq!(s.rows.len(), MAX_REPEAT);
+        assert_eq!(s.rows[0].len(), MAX_COLUMNS);
+    }
+
+    #[test]
+    fn clamps_empty_rows() {
+        let s = sheet(
+            r#"<table:table-row><table:table-cell><text:p>a</text:p></table:table-cell></table:table-row>
+               <table:table-row table:number-rows-repeated="1000000"><table:table-cell/></table:table-row>
+               <table:table-row><table:table-cell><text:p>b</text:p></table:table-cell></table:table-row>"#,
+        );
+        assert_eq!(s.rows.len(), MAX_EMPTY_REPEAT + 2);
+    }
+}
@@ -0,0 +1,605 @@
+//! Out-of-process backends.
+//!
+//! Plugins are executables named `autoview-backend-NAME` on the `PATH`.
+//! AutoView runs each one with `--handshake` (without input, and killing it
+//! after [HANDSHAKE_TIMEOUT]), and it prints a JSON object describing what it
+//! can view:
+//!
+//! ```json
+//! {"protocol": 1, "mime_types": ["text/x-foo", "image/*"], "views": ["meta", "full"]}
+//! ```
+//!
+//! To view a file, AutoView runs `autoview-backend-NAME --view VIEW
+//! --mime-type TYPE PATH` (also without input, and killing it after a time
+//! limit that depends on the view speed), and the plugin prints the view's
+//! output as JSON, in the same form as `--format json` output.  Field keys are
+//! optional, and derived from the labels if they are missing.  Top-level
+//! `meta` blocks get the common file metadata added, as with built-in
+//! backends:
+//!
+//! ```json
+//! {"paged": false, "blocks": [
+//!   {"type": "meta", "description": "Foo data", "size": 1024,
+//!    "fields": [{"key": "records", "label": "Records", "value": 12},
+//!               {"key": "page_size", "label": "Page size",
+//!                "value": {"width": 80, "height": 24}}]},
+//!   {"type": "section", "title": "Records", "blocks": [
+//!     {"type": "table", "columns": ["Name", "Count"], "rows": [["a", 1]]}
+//!   ]},
+//!   {"type": "text", "lines": ["first line", "second line"]}
+//! ]}
+//! ```
+//!
+//! Plugins are only discovered when no configured viewer claims a file, and
+//! handshake results are cached in `autoview/plugins.json` in the XDG cache
+//! directory until the plugin executable changes.
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::ffi::OsString;
+use std::fs::{create_dir_all, read, read_dir, write, Metadata};
+use std::os::unix::fs::PermissionsExt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::{Duration, SystemTime};
+
+use log::*;
+use serde::{Deserialize, Serialize};
+
+use crate::fileinfo::file_meta_fields;
+use crate::interface::*;
+use crate::mime::matches_type;
+use crate::programs::{run_with_timeout, ProgramError};
+
+/// Prefix for plugin executable names.
+const PLUGIN_PREFIX: &str = "autoview-backend-";
+/// Version of the plugin protocol.
+const PROTOCOL_VERSION: u32 = 1;
+/// How long to wait for a plugin's handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(2);
+/// How long to wait for a plugin's view by default.
+const VIEW_TIMEOUT: Duration = Duration::from_secs(10);
+/// How long to wait for a plugin's view with `--slow`.
+const SLOW_VIEW_TIMEOUT: Duration = Duration::from_secs(60);
+
+static PLUGINS: OnceLock<Vec<PluginBackend>> = OnceLock::new();
+
+/// A backend provided by a plugin executable.
+pub struct PluginBackend {
+    name: String,
+    path: PathBuf,
+    mime_types: Vec<String>,
+    views: Vec<ViewType>,
+}
+
+struct PluginView {
+    path: PathBuf,
+    view: ViewType,
+}
+
+/// Plugin handshake response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Handshake {
+    protocol: u32,
+    mime_types: Vec<String>,
+    views: Vec<ViewType>,
+}
+
+/// Cached handshakes, by plugin path.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct HandshakeCache {
+    plugins: HashMap<PathBuf, CachedHandshake>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedHandshake {
+    modified: SystemTime,
+    size: u64,
+    /// The handshake, or `None` if the plugin failed it.
+    handshake: Option<Handshake>,
+}
+
+/// Plugin view output.
+#[derive(Debug, Deserialize)]
+struct PluginOutput {
+    #[serde(default)]
+    paged: bool,
+    blocks: Vec<PluginBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum PluginBlock {
+    Meta {
+        description: String,
+        #[serde(default)]
+        size: Option<u64>,
+        #[serde(default)]
+        fields: Vec<PluginField>,
+    },
+    Section {
+        title: String,
+        blocks: Vec<PluginBlock>,
+    },
+    Fields {
+        fields: Vec<PluginField>,
+    },
+    Table {
+        columns: Vec<String>,
+        rows: Vec<Vec<PluginValue>>,
+    },
+    Text {
+        lines: Vec<String>,
+    },
+}
+
+#[derive(Debug, Deserialize)]
+struct PluginField {
+    /// Machine-readable key, derived from the label if it is missing.
+    #[serde(default)]
+    key: Option<String>,
+    label: String,
+    value: PluginValue,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum PluginValue {
+    Integer(i64),
+    Number(f64),
+    Bool(bool),
+    Text(String),
+    Dimensions { width: u32, height: u32 },
+    Record(Vec<PluginField>),
+    List(Vec<PluginValue>),
+}
+
+/// Get the plugins on the `PATH`, discovering them the first time.
+pub fn plugins() -> &'static [PluginBackend] {
+    PLUGINS.get_or_init(|| {
+        let path = env::var_os("PATH").unwrap_or_default();
+        discover(&path, cache_path().as_deref())
+    })
+}
+
+/// Get the path to the handshake cache.
+fn cache_path() -> Option<PathBuf> {
+    let dir = match env::var_os("XDG_CACHE_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".cache"),
+    };
+    Some(dir.join("autoview").join("plugins.json"))
+}
+
+/// Find plugin executables in a search path.  Earlier directories take
+/// precedence, as with program lookup.  Handshakes are only run for plugins
+/// that are new or have changed since they were cached.
+fn discover(search: &OsString, cache_file: Option<&Path>) -> Vec<PluginBackend> {
+    let mut cache = cache_file.map(HandshakeCache::load).unwrap_or_default();
+    let mut updated = HandshakeCache::default();
+    let mut changed = false;
+    let mut seen = HashSet::new();
+    let mut plugins = Vec::new();
+    for dir in env::split_paths(search) {
+        let entries = match read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(_) => continue,
+        };
+        let mut found: Vec<_> = entries
+            .filter_map(|e| e.ok())
+            .filter_map(|e| {
+                let name = e
+                    .file_name()
+                    .to_str()?
+                    .strip_prefix(PLUGIN_PREFIX)?
+                    .to_string();
+                Some((name, e.path()))
+            })
+            .filter_map(|(name, path)| {
+                let meta = path.metadata().ok().filter(is_executable)?;
+                Some((name, path, meta))
+            })
+            .collect();
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+        for (name, path, meta) in found {
+            if !seen.insert(name.clone()) {
+                continue;
+            }
+            let cached = cache.plugins.remove(&path).filter(|c| c.matches(&meta));
+            let entry = match cached {
+                Some(entry) => {
+                    debug!("using cached handshake for {}", path.display());
+                    entry
+                }
+                None => {
+                    changed = true;
+                    let handshake = handshake(&path)
+                        .inspect_err(|e| warn!("ignoring plugin {}: {}", path.display(), e))
+                        .ok();
+                    CachedHandshake {
+                        modified: meta.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+                        size: meta.len(),
+                        handshake,
+                    }
+                }
+            };
+            if let Some(hs) = &entry.handshake {
+                info!("found plugin {} at {}", name, path.display());
+                plugins.push(PluginBackend {
+                    name,
+                    path: path.clone(),
+                    mime_types: hs.mime_types.clone(),
+                    views: hs.views.clone(),
+                });
+            }
+            updated.plugins.insert(path, entry);
+        }
+    }
+    // entries left over are for plugins that were removed
+    changed |= !cache.plugins.is_empty();
+    if let (true, Some(file)) = (changed, cache_file) {
+        updated.save(file);
+    }
+    plugins
+}
+
+fn is_executable(meta: &Metadata) -> bool {
+    meta.is_file() && meta.permissions().mode() & 0o111 != 0
+}
+
+impl HandshakeCache {
+    fn load(file: &Path) -> HandshakeCache {
+        let data = match read(file) {
+            Ok(data) => data,
+            Err(e) => {
+                debug!("cannot read {}: {}", file.display(), e);
+                return HandshakeCache::default();
+            }
+        };
+        serde_json::from_slice(&data).unwrap_or_else(|e| {
+            warn!("ignoring invalid plugin cache {}: {}", file.display(), e);
+            HandshakeCache::default()
+        })
+    }
+
+    fn save(&self, file: &Path) {
+        debug!("saving plugin cache to {}", file.display());
+        let res = file
+            .parent()
+            .map_or(Ok(()), create_dir_all)
+            .and_then(|_| write(file, serde_json::to_vec(self)?));
+        if let Err(e) = res {
+            warn!("cannot save plugin cache {}: {}", file.display(), e);
+        }
+    }
+}
+
+impl CachedHandshake {
+    /// Check whether the cached handshake is for the current executable.
+    fn matches(&self, meta: &Metadata) -> bool {
+        meta.modified().is_ok_and(|m| m == self.modified) && meta.len() == self.size
+    }
+}
+
+fn handshake(path: &Path) -> Result<Handshake, ViewError> {
+    debug!("running handshake for {}", path.display());
+    let mut cmd = Command::new(path);
+    cmd.arg("--handshake");
+    let output = run_with_timeout(cmd, HANDSHAKE_TIMEOUT)?;
+    ProgramError::check(output.status)?;
+    parse_handshake(&output.stdout)
+}
+
+fn parse_handshake(json: &[u8]) -> Result<Handshake, ViewError> {
+    let hs: Handshake = serde_json::from_slice(json).map_err(ViewError::wrap)?;
+    if hs.protocol != PROTOCOL_VERSION {
+        return Err(format!("unsupported protocol version {}", hs.protocol).into());
+    }
+    Ok(hs)
+}
+
+impl FileViewer for PluginBackend {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn make_view(&self, req: &FileRequest, mode: &Option<ViewType>) -> Option<Box<dyn FileView>> {
+        if !self
+            .mime_types
+            .iter()
+            .any(|pat| matches_type(&req.mime_type, pat))
+        {
+            return None;
+        }
+        let view = match mode {
+            Some(view) if self.views.contains(view) => view.clone(),
+            Some(_) => return None,
+            // default to the most complete view available
+            None => [ViewType::Full, ViewType::Head, ViewType::Meta]
+                .into_iter()
+                .find(|v| self.views.contains(v))?,
+        };
+        Some(Box::new(PluginView {
+            path: self.path.clone(),
+            view,
+        }))
+    }
+}
+
+impl FileView for PluginView {
+    fn render(&self, req: &FileRequest, options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        let view = match self.view {
+            ViewType::Meta => "meta",
+            ViewType::Head => "head",
+            ViewType::Full => "full",
+        };
+        let mut cmd = Command::new(&self.path);
+        cmd.arg("--view")
+            .arg(view)
+            .arg("--mime-type")
+            .arg(&req.mime_type)
+            .arg(&req.path);
+        info!("running plugin {:?}", cmd);
+        let output = run_with_timeout(cmd, view_timeout(&options.speed))?;
+        ProgramError::check(output.status)?;
+        let output: PluginOutput =
+            serde_json::from_slice(&output.stdout).map_err(ViewError::wrap)?;
+        let blocks = output
+            .blocks
+            .into_iter()
+            .map(|block| match block.into_block() {
+                Block::Meta(mut info) => {
+                    info.fields.extend(file_meta_fields(req, options));
+                    Block::Meta(info)
+                }
+                block => block,
+            })
+            .collect();
+        Ok(ViewOutput {
+            blocks,
+            paged: output.paged,
+        })
+    }
+}
+
+/// Get the time limit for a plugin's view.
+fn view_timeout(speed: &ViewSpeed) -> Duration {
+    match speed {
+        ViewSpeed::Fast => HANDSHAKE_TIMEOUT,
+        ViewSpeed::Default => VIEW_TIMEOUT,
+        ViewSpeed::Slow => SLOW_VIEW_TIMEOUT,
+    }
+}
+
+impl PluginBlock {
+    fn into_block(self) -> Block {
+        match self {
+            PluginBlock::Meta {
+                description,
+                size,
+                fields,
+            } => {
+                let mut info = MetaInfo::new(description);
+                info.size = size;
+                info.fields = fields.into_iter().map(PluginField::into_field).collect();
+                Block::Meta(info)
+            }
+            PluginBlock::Section { title, blocks } => Block::Section(
+                title,
+                blocks.into_iter().map(PluginBlock::into_block).collect(),
+            ),
+            PluginBlock::Fields { fields } => {
+                Block::Fields(fields.into_iter().map(PluginField::into_field).collect())
+            }
+            PluginBlock::Table { columns, rows } => Block::Table(Table {
+                columns: columns
+                    .into_iter()
+                    .map(|c| Column::new(c, Align::Left))
+                    .collect(),
+                rows: rows
+                    .into_iter()
+                    .map(|r| r.into_iter().map(PluginValue::into_value).collect())
+                    .collect(),
+                headings: true,
+            }),
+            PluginBlock::Text { lines } => Block::Text(lines),
+        }
+    }
+}
+
+impl PluginField {
+    fn into_field(self) -> Field {
+        match self.key {
+            Some(key) => Field::new(&key, self.label, self.value.into_value()),
+            None => Field::from_label(self.label, self.value.into_value()),
+        }
+    }
+}
+
+impl PluginValue {
+    fn into_value(self) -> Value {
+        match self {
+            PluginValue::Integer(n) => Value::Integer(n),
+            PluginValue::Number(x) => Value::Text(x.to_string()),
+            PluginValue::Bool(b) => Value::Text(if b { "yes" } else { "no" }.into()),
+            PluginValue::Text(s) => Value::Text(s),
+            PluginValue::Dimensions { width, height } => Value::Dimensions(width, height),
+            // an empty array has no fields to mark it as a record
+            PluginValue::Record(fields) if fields.is_empty() => Value::List(Vec::new()),
+            PluginValue::Record(fields) => {
+                Value::Record(fields.into_iter().map(PluginField::into_field).collect())
+            }
+            PluginValue::List(items) => {
+                Value::List(items.into_iter().map(PluginValue::into_value).collect())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{set_permissions, write, Permissions};
+    use std::time::Instant;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn script(dir: &TempDir, name: &str, body: &str) -> PathBuf {
+        let path = dir.path().join(name);
+        write(&path, format!("#!/bin/sh\n{}\n", body)).unwrap();
+        set_permissions(&path, Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    #[test]
+    fn parses_handshake() {
+        let hs = parse_handshake(
+            br#"{"protocol": 1, "mime_types": ["text/csv"], "views": ["meta", "full"]}"#,
+        )
+        .unwrap();
+        assert_eq!(hs.mime_types, ["text/csv"]);
+        assert_eq!(hs.views, [ViewType::Meta, ViewType::Full]);
+
+        assert!(parse_handshake(br#"{"protocol": 2, "mime_types": [], "views": []}"#).is_err());
+        assert!(parse_handshake(br#"{"protocol": 1}"#).is_err());
+        assert!(parse_handshake(b"usage: plugin FILE").is_err());
+    }
+
+    #[test]
+    fn discovers_plugins() {
+        let dir = TempDir::new().unwrap();
+        script(
+            &dir,
+            "autoview-backend-csv",
+            r#"echo '{"protocol": 1, "mime_types": ["text/csv"], "views": ["full"]}'"#,
+        );
+        script(&dir, "autoview-backend-broken", "exit 1");
+        script(&dir, "unrelated", "exit 0");
+        let found = discover(&dir.path().as_os_str().to_os_string(), None);
+        let names: Vec<_> = found.iter().map(|p| p.name()).collect();
+        assert_eq!(names, ["csv"]);
+    }
+
+    #[test]
+    fn caches_handshakes() {
+        let dir = TempDir::new().unwrap();
+        let log = dir.path().join("runs");
+        let body = format!(
+            "echo run >> '{}'\necho '{{\"protocol\": 1, \"mime_types\": [\"text/csv\"], \"views\": [\"full\"]}}'",
+            log.display()
+        );
+        let path = script(&dir, "autoview-backend-csv", &body);
+        script(&dir, "autoview-backend-broken", "exit 1");
+        let search = dir.path().as_os_str().to_os_string();
+        let cache = dir.path().join("cache").join("plugins.json");
+        let runs = || read(&log).unwrap().len() / "run\n".len();
+
+        assert_eq!(discover(&search, Some(&cache)).len(), 1);
+        assert_eq!(discover(&search, Some(&cache)).len(), 1);
+        assert_eq!(runs(), 1);
+
+        // a changed plugin runs its handshake again
+        write(&path, format!("#!/bin/sh\n{}\n\n", body)).unwrap();
+        assert_eq!(discover(&search, Some(&cache)).len(), 1);
+        assert_eq!(runs(), 2);
+    }
+
+    #[test]
+    fn kills_slow_handshake() {
+        let dir = TempDir::new().unwrap();
+        let path = script(&dir, "autoview-backend-slow", "read line\nsleep 30");
+        let start = Instant::now();
+        assert!(handshake(&path).is_err());
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn views_with_file_metadata() {
+        let dir = TempDir::new().unwrap();
+        let path = script(
+            &dir,
+            "autoview-backend-foo",
+            r#"echo '{"blocks": [{"type": "meta", "description": "Foo data", "fields": [
+                {"key": "tags", "label": "Tags", "value": []}]}]}'"#,
+        );
+        let file = dir.path().join("data.foo");
+        write(&file, "foo\n").unwrap();
+        let req = FileRequest {
+            path: file.clone(),
+            meta: Some(file.metadata().unwrap()),
+            link_target: None,
+            mime_type: "text/x-foo".into(),
+        };
+        let view = PluginView {
+            path,
+            view: ViewType::Meta,
+        };
+        let output = view.render(&req, &ViewOptions::default()).unwrap();
+        match &output.blocks[..] {
+            [Block::Meta(info)] => {
+                assert!(matches!(&info.fields[0].value, Value::List(l) if l.is_empty()));
+                assert!(info.fields.iter().any(|f| f.key == "permissions"));
+            }
+            _ => panic!("unexpected blocks"),
+        }
+    }
+
+    #[test]
+    fn kills_slow_view() {
+        let dir = TempDir::new().unwrap();
+        let path = script(&dir, "autoview-backend-slow", "sleep 30");
+        let file = dir.path().join("data.foo");
+        write(&file, "foo\n").unwrap();
+        let req = FileRequest {
+            path: file,
+            meta: None,
+            link_target: None,
+            mime_type: "text/x-foo".into(),
+        };
+        let view = PluginView {
+            path,
+            view: ViewType::Full,
+        };
+        let options = ViewOptions {
+            speed: ViewSpeed::Fast,
+            ..Default::default()
+        };
+        let start = Instant::now();
+        assert!(view.render(&req, &options).is_err());
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn converts_blocks() {
+        let output: PluginOutput = serde_json::from_str(
+            r#"{"blocks": [
+                {"type": "meta", "description": "Foo data", "size": null, "fields": [
+                    {"label": "Author", "value": "A"},
+                    {"label": "Author", "value": "B"},
+                    {"label": "Page", "value": {"width": 80, "height": 24}},
+                    {"label": "Extra", "value": [{"label": "Ok", "value": true}]}
+                ]},
+                {"type": "table", "columns": ["Name", "Count"], "rows": [["a", 1]]}
+            ]}"#,
+        )
+        .unwrap();
+        assert!(!output.paged);
+        let blocks: Vec<_> = output
+            .blocks
+            .into_iter()
+            .map(PluginBlock::into_block)
+            .collect();
+        match &blocks[..] {
+            [Block::Meta(info), Block::Table(table)] => {
+                let labels: Vec<_> = info.fields.iter().map(|f| f.label.as_str()).collect();
+                assert_eq!(labels, ["Author", "Author", "Page", "Extra"]);
+                assert!(matches!(info.fields[2].value, Value::Dimensions(80, 24)));
+                assert!(matches!(&info.fields[3].value, Value::Record(r) if r.len() == 1));
+                assert_eq!(table.rows.len(), 1);
+                assert!(matches!(table.rows[0][1], Value::Integer(1)));
+            }
+            _ => panic!("unexpected blocks"),
+        }
+    }
+}
@@ -0,0 +1,74 @@
+//! Dangling symbolic links.
+//!
+//! Links to existing files are viewed as their targets; this backend handles
+//! links whose target does not exist.
+use std::fs::symlink_metadata;
+
+use crate::interface::*;
+
+/// Backend for dangling symbolic links.
+pub struct SymlinkBackend;
+
+struct DanglingView;
+
+impl FileViewer for SymlinkBackend {
+    fn name(&self) -> &'static str {
+        "symlink"
+    }
+
+    fn make_view(&self, req: &FileRequest, _mode: &Option<ViewType>) -> Option<Box<dyn FileView>> {
+        if req.is_dangling() {
+            Some(Box::new(DanglingView))
+        } else {
+            None
+        }
+    }
+}
+
+impl MetaView for DanglingView {
+    fn describe(&self, req: &FileRequest, _options: &ViewOptions) -> Result<MetaInfo, ViewError> {
+        let mut info = MetaInfo::new("broken symbolic link");
+        // the size of the link itself, as `ls -l` reports it
+        info.size = symlink_metadata(&req.path).ok().map(|m| m.len());
+        Ok(info)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::file_request;
+
+    #[test]
+    fn describes_dangling_link() {
+        let dir = TempDir::new().unwrap();
+        let link = dir.path().join("broken");
+        symlink("missing.txt", &link).unwrap();
+        let req = file_request(&link).unwrap();
+        let view = SymlinkBackend.make_view(&req, &None).unwrap();
+        let info = DanglingView
+            .describe(&req, &ViewOptions::default())
+            .unwrap();
+        assert_eq!(info.size, Some("missing.txt".len() as u64));
+
+        let output = view.render(&req, &ViewOptions::default()).unwrap();
+        let info = match &output.blocks[0] {
+            Block::Meta(info) => info,
+            _ => panic!("expected metadata"),
+        };
+        let labels: Vec<_> = info.fields.iter().map(|f| f.label.as_str()).collect();
+        for label in [
+            "Permissions",
+            "Owner",
+            "Modified",
+            "Link target",
+            "Link status",
+        ] {
+            assert!(labels.contains(&label), "missing {}", label);
+        }
+    }
+}
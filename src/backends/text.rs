@@ -1,34 +1,35 @@
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 
-use bat::line_range::{LineRange, LineRanges};
-use bat::PrettyPrinter;
 use log::*;
 
+use crate::interface::*;
 use crate::mime::mime_db;
-use crate::pager::page_file;
-use crate::styling::FIELD_NAME;
-use crate::{
-    interface::*,
-    styling::{styled, FILE_SIZE, FILE_TYPE},
-};
+use crate::styling::FILE_TYPE;
 
 /// Non-specialized text file backend.
 pub struct TextBackend {}
 struct TextMeta;
-struct TextView {
-    lines: Option<usize>,
+pub(super) struct TextView {
+    pub(super) lines: Option<usize>,
 }
 
+pub(super) const HEAD_LINES: usize = 15;
 const BIG_FILE: u64 = 32 * 1024 * 1024;
 
 impl FileViewer for TextBackend {
+    fn name(&self) -> &'static str {
+        "text"
+    }
+
     fn make_view(&self, req: &FileRequest, mode: &Option<ViewType>) -> Option<Box<dyn FileView>> {
         let db = mime_db();
         if db.is_subtype(&req.mime_type, "text/plain") {
             match mode {
                 Some(ViewType::Meta) => Some(Box::new(TextMeta)),
-                Some(ViewType::Head) => Some(Box::new(TextView { lines: Some(15) })),
+                Some(ViewType::Head) => Some(Box::new(TextView {
+                    lines: Some(HEAD_LINES),
+                })),
                 _ => Some(Box::new(TextView { lines: None })),
             }
         } else {
@@ -37,52 +38,41 @@ impl FileViewer for TextBackend {
     }
 }
 
-impl FileView for TextMeta {
-    fn display(&self, req: &FileRequest, options: &ViewOptions) -> Result<(), ViewError> {
+impl MetaView for TextMeta {
+    fn describe(&self, req: &FileRequest, options: &ViewOptions) -> Result<MetaInfo, ViewError> {
         let db = mime_db();
-
-        print!("text file of type {}", styled(&req.mime_type, &FILE_TYPE));
-
-        if let Some(lines) = self.count_lines(req, options) {
-            print!(
-                " with {} lines",
-                styled(format!("{}", friendly::integer(lines)), &FILE_SIZE)
-            );
-        }
-        if let Some(bytes) = req.file_size() {
-            print!(
-                " ({})",
-                styled(format!("{}", friendly::bytes(bytes)), &FILE_SIZE)
-            );
-        }
-        println!();
-
-        if let Some(desc) = db.description(&req.mime_type) {
-            println!("{}: {}", styled("Text description", &FIELD_NAME), desc);
-        };
-        if let Some(size) = req.file_size() {
-            println!("{}: {}", styled("Size", &FIELD_NAME), friendly::bytes(size));
-        }
-        Ok(())
+        let mut info = MetaInfo::new(db.description(&req.mime_type).unwrap_or("text file"));
+        info.summary = self
+            .count_lines(req, options)
+            .map(|n| Field::new("lines", "Lines", Value::Count(n as u64, "lines")));
+        info.size = req.file_size();
+        info.add(
+            "mime_type",
+            "MIME type",
+            Value::Styled(req.mime_type.clone(), &FILE_TYPE),
+        );
+        Ok(info)
     }
 }
 
 impl FileView for TextView {
-    fn display(&self, req: &FileRequest, _options: &ViewOptions) -> Result<(), ViewError> {
+    fn render(&self, req: &FileRequest, _options: &ViewOptions) -> Result<ViewOutput, ViewError> {
         let size = req.file_size().unwrap_or_default();
         if size > BIG_FILE && self.lines.is_none() {
             info!("paging large file {:?}", req.path);
-            page_file(&req.path)?;
+            Ok(ViewOutput::paged(vec![Block::Raw(Passthrough::File(
+                req.path.clone(),
+            ))]))
         } else {
-            let mut printer = PrettyPrinter::new();
-            printer.input_file(&req.path);
-            if let Some(lines) = self.lines {
-                printer.line_ranges(LineRanges::from(vec![LineRange::new(1, lines)]));
-            }
-            printer.paging_mode(bat::PagingMode::QuitIfOneScreen);
-            printer.print().map_err(ViewError::wrap)?;
+            let source = Block::Source {
+                path: req.path.clone(),
+                lines: self.lines,
+            };
+            Ok(ViewOutput {
+                blocks: vec![source],
+                paged: self.lines.is_none(),
+            })
         }
-        Ok(())
     }
 }
 
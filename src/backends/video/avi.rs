@@ -0,0 +1,156 @@
+//! AVI (RIFF) containers.
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::time::Duration;
+
+use super::*;
+
+/// Largest header list we are willing to load.
+const MAX_HEADER: u32 = 16 * 1024 * 1024;
+
+/// Iterator over RIFF chunks in a buffer.
+fn chunks(data: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    let mut cur = data;
+    std::iter::from_fn(move || {
+        let id = cur.get(0..4)?;
+        let size = le_u32(cur, 4)? as usize;
+        let body = cur.get(8..8 + size)?;
+        // chunks are padded to even sizes
+        let next = (8 + size + (size & 1)).min(cur.len());
+        cur = &cur[next..];
+        Some((id, body))
+    })
+}
+
+pub(super) fn read_avi(path: &Path) -> Result<VideoInfo, ViewError> {
+    let mut file = File::open(path)?;
+    // RIFF header, followed by the hdrl list header
+    let mut header = [0u8; 24];
+    file.read_exact(&mut header)?;
+    if &header[12..16] != b"LIST" || &header[20..24] != b"hdrl" {
+        return Err("AVI file does not start with header list".into());
+    }
+    let size = le_u32(&header, 16).unwrap_or_default();
+    if !(4..=MAX_HEADER).contains(&size) {
+        return Err("invalid AVI header size".into());
+    }
+    let mut hdrl = vec![0; size as usize - 4];
+    file.read_exact(&mut hdrl)?;
+
+    let mut info = VideoInfo {
+        container: "AVI".into(),
+        duration: None,
+        title: None,
+        tracks: Vec::new(),
+    };
+
+    for (id, body) in chunks(&hdrl) {
+        if id == b"avih" {
+            let us_per_frame = le_u32(body, 0).unwrap_or_default();
+            let frames = le_u32(body, 16).unwrap_or_default();
+            if us_per_frame > 0 {
+                info.duration = Some(Duration::from_micros(us_per_frame as u64 * frames as u64));
+            }
+        }
+    }
+
+    let streams = chunks(&hdrl)
+        .filter(|(id, body)| *id == b"LIST" && body.get(0..4) == Some(b"strl"))
+        .map(|(_, body)| &body[4..]);
+    for strl in streams {
+        if let Some(track) = read_stream(strl) {
+            info.tracks.push(track);
+        }
+    }
+
+    Ok(info)
+}
+
+fn read_stream(strl: &[u8]) -> Option<Track> {
+    let strh = chunks(strl).find(|(id, _)| *id == b"strh")?.1;
+    let strf = chunks(strl).find(|(id, _)| *id == b"strf").map(|(_, b)| b);
+    let name = chunks(strl)
+        .find(|(id, _)| *id == b"strn")
+        .map(|(_, b)| {
+            String::from_utf8_lossy(b)
+                .trim_matches(char::from(0))
+                .to_string()
+        })
+        .filter(|n| !n.is_empty());
+
+    let kind = strh.get(0..4)?;
+    let handler = strh.get(4..8)?;
+    let scale = le_u32(strh, 20).unwrap_or_default();
+    let rate = le_u32(strh, 24).unwrap_or_default();
+
+    let (kind, codec) = match kind {
+        b"vids" => {
+            // BITMAPINFOHEADER
+            let strf = strf.unwrap_or_default();
+            let width = le_u32(strf, 4).unwrap_or_default();
+            let height = le_u32(strf, 8).unwrap_or_default() as i32;
+            let compression = strf.get(16..20).unwrap_or(handler);
+            let fps = if scale > 0 {
+                Some(rate as f64 / scale as f64)
+            } else {
+                None
+            };
+            (
+                TrackKind::Video {
+                    width,
+                    height: height.unsigned_abs(),
+                    fps,
+                },
+                video_codec(compression),
+            )
+        }
+        b"auds" => {
+            // WAVEFORMATEX
+            let strf = strf.unwrap_or_default();
+            let format = le_u16(strf, 0).unwrap_or_default();
+            (
+                TrackKind::Audio {
+                    channels: le_u16(strf, 2).map(|c| c as u32),
+                    sample_rate: le_u32(strf, 4).map(|r| r as f64),
+                },
+                audio_codec(format),
+            )
+        }
+        b"txts" => (TrackKind::Subtitle, fourcc(handler)),
+        other => (TrackKind::Other(fourcc(other)), fourcc(handler)),
+    };
+
+    Some(Track {
+        kind,
+        codec,
+        language: None,
+        name,
+    })
+}
+
+fn video_codec(code: &[u8]) -> String {
+    match code.to_ascii_uppercase().as_slice() {
+        b"XVID" | b"DIVX" | b"DX50" | b"FMP4" | b"MP4V" => "MPEG-4 Part 2".into(),
+        b"H264" | b"X264" | b"AVC1" => "H.264".into(),
+        b"HEVC" | b"H265" => "H.265".into(),
+        b"MJPG" => "Motion JPEG".into(),
+        b"MPG2" => "MPEG-2".into(),
+        b"\0\0\0\0" => "uncompressed".into(),
+        _ => fourcc(code),
+    }
+}
+
+fn audio_codec(format: u16) -> String {
+    match format {
+        0x0001 => "PCM".into(),
+        0x0003 => "PCM (float)".into(),
+        0x0050 => "MPEG audio".into(),
+        0x0055 => "MP3".into(),
+        0x00FF | 0x1610 => "AAC".into(),
+        0x2000 => "AC-3".into(),
+        0x2001 => "DTS".into(),
+        0x0161 | 0x0162 => "WMA".into(),
+        n => format!("format 0x{:04x}", n),
+    }
+}
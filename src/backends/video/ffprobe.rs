@@ -0,0 +1,176 @@
+//! Other containers, described with `ffprobe`.
+use std::collections::HashMap;
+use std::process::Stdio;
+
+use serde::Deserialize;
+
+use super::*;
+use crate::programs::ProgramError;
+
+/// Output of `ffprobe -show_format -show_streams`.
+#[derive(Debug, Deserialize)]
+struct Probe {
+    #[serde(default)]
+    streams: Vec<Stream>,
+    format: Format,
+}
+
+#[derive(Debug, Deserialize)]
+struct Format {
+    format_long_name: Option<String>,
+    format_name: Option<String>,
+    duration: Option<String>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Stream {
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    #[serde(default)]
+    tags: HashMap<String, String>,
+}
+
+pub(super) fn read_ffprobe(req: &FileRequest) -> Result<VideoInfo, ViewError> {
+    let mut cmd = find_program("ffprobe")?.ok_or_else(|| {
+        ViewError::from(format!(
+            "unsupported video format {} and ffprobe not found",
+            req.mime_type
+        ))
+    })?;
+    info!("describing video with ffprobe");
+    cmd.args(["-v", "error", "-print_format", "json"])
+        .args(["-show_format", "-show_streams"])
+        .arg(&req.path)
+        .stdin(Stdio::null());
+    let output = cmd.output()?;
+    ProgramError::check(output.status)?;
+    parse_probe(&output.stdout)
+}
+
+fn parse_probe(json: &[u8]) -> Result<VideoInfo, ViewError> {
+    let probe: Probe = serde_json::from_slice(json).map_err(ViewError::wrap)?;
+    let format = probe.format;
+    let duration = format
+        .duration
+        .and_then(|d| d.parse::<f64>().ok())
+        .filter(|d| *d > 0.0)
+        .map(seconds)
+        .transpose()?;
+    Ok(VideoInfo {
+        container: format
+            .format_long_name
+            .or(format.format_name)
+            .unwrap_or_else(|| "unknown".into()),
+        duration,
+        title: format.tags.get("title").cloned(),
+        tracks: probe.streams.into_iter().map(Stream::into_track).collect(),
+    })
+}
+
+impl Stream {
+    fn into_track(mut self) -> Track {
+        let kind = match self.codec_type.as_deref() {
+            Some("video") => TrackKind::Video {
+                width: self.width.unwrap_or_default(),
+                height: self.height.unwrap_or_default(),
+                fps: self.avg_frame_rate.as_deref().and_then(parse_rate),
+            },
+            Some("audio") => TrackKind::Audio {
+                sample_rate: self.sample_rate.and_then(|r| r.parse().ok()),
+                channels: self.channels,
+            },
+            Some("subtitle") => TrackKind::Subtitle,
+            Some(other) => TrackKind::Other(other.into()),
+            None => TrackKind::Other("track".into()),
+        };
+        Track {
+            kind,
+            codec: codec_name(self.codec_name.as_deref().unwrap_or("unknown")),
+            language: self.tags.remove("language").filter(|l| l != "und"),
+            name: self.tags.remove("title").filter(|n| !n.is_empty()),
+        }
+    }
+}
+
+/// Parse a frame rate fraction such as `30000/1001`.
+fn parse_rate(rate: &str) -> Option<f64> {
+    let (num, den) = rate.split_once('/')?;
+    let num: f64 = num.parse().ok()?;
+    let den: f64 = den.parse().ok()?;
+    if num > 0.0 && den > 0.0 {
+        Some(num / den)
+    } else {
+        None
+    }
+}
+
+fn codec_name(name: &str) -> String {
+    let name = match name {
+        "h264" => "H.264",
+        "hevc" => "H.265",
+        "av1" => "AV1",
+        "vp8" => "VP8",
+        "vp9" => "VP9",
+        "mpeg2video" => "MPEG-2",
+        "mpeg4" => "MPEG-4 Part 2",
+        "theora" => "Theora",
+        "aac" => "AAC",
+        "ac3" => "AC-3",
+        "eac3" => "E-AC-3",
+        "mp3" => "MP3",
+        "mp2" => "MP2",
+        "opus" => "Opus",
+        "vorbis" => "Vorbis",
+        "flac" => "FLAC",
+        "subrip" => "SubRip",
+        "ass" => "ASS/SSA",
+        "webvtt" => "WebVTT",
+        "dvb_subtitle" => "DVB subtitles",
+        _ => name,
+    };
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_probe() {
+        let json = br#"{
+            "streams": [
+                {"codec_type": "video", "codec_name": "h264", "width": 1280, "height": 720,
+                 "avg_frame_rate": "30000/1001", "tags": {"language": "und"}},
+                {"codec_type": "audio", "codec_name": "aac", "sample_rate": "48000",
+                 "channels": 2, "tags": {"language": "eng", "title": "Main"}}
+            ],
+            "format": {"format_name": "mpegts", "format_long_name": "MPEG-TS",
+                       "duration": "10.500000", "tags": {"title": "Clip"}}
+        }"#;
+        let info = parse_probe(json).unwrap();
+        assert_eq!(info.container, "MPEG-TS");
+        assert_eq!(info.duration, Some(Duration::from_millis(10500)));
+        assert_eq!(info.title.as_deref(), Some("Clip"));
+        assert_eq!(
+            info.tracks[0].describe(),
+            "video: H.264 1280x720 @ 29.970 fps"
+        );
+        assert_eq!(
+            info.tracks[1].describe(),
+            "audio: AAC 48000 Hz, stereo [eng] \"Main\""
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_duration() {
+        let json = br#"{"format": {"duration": "1e300"}}"#;
+        assert!(parse_probe(json).is_err());
+    }
+}
@@ -0,0 +1,344 @@
+//! Matroska and WebM containers.
+use std::fs::File;
+use std::io::{self, BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+use super::*;
+
+const EBML_HEADER: u32 = 0x1A45DFA3;
+const DOC_TYPE: u32 = 0x4282;
+const SEGMENT: u32 = 0x18538067;
+const INFO: u32 = 0x1549A966;
+const TRACKS: u32 = 0x1654AE6B;
+const CLUSTER: u32 = 0x1F43B675;
+const TIMESTAMP_SCALE: u32 = 0x2AD7B1;
+const DURATION: u32 = 0x4489;
+const TITLE: u32 = 0x7BA9;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_TYPE: u32 = 0x83;
+const CODEC_ID: u32 = 0x86;
+const LANGUAGE: u32 = 0x22B59C;
+const LANGUAGE_BCP47: u32 = 0x22B59D;
+const NAME: u32 = 0x536E;
+const DEFAULT_DURATION: u32 = 0x23E383;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+const AUDIO: u32 = 0xE1;
+const SAMPLING_FREQUENCY: u32 = 0xB5;
+const CHANNELS: u32 = 0x9F;
+
+/// Marker for elements of unknown size.
+const UNKNOWN_SIZE: u64 = u64::MAX;
+/// Largest header element we are willing to load.
+const MAX_ELEMENT: u64 = 16 * 1024 * 1024;
+
+pub(super) fn read_mkv(path: &Path) -> Result<VideoInfo, ViewError> {
+    let mut file = BufReader::new(File::open(path)?);
+
+    let (id, size) = read_header(&mut file)?.ok_or("empty Matroska file")?;
+    if id != EBML_HEADER || size == UNKNOWN_SIZE {
+        return Err("invalid EBML header".into());
+    }
+    let header = read_body(&mut file, size)?;
+    let doc_type = elements(&header)
+        .find(|(id, _)| *id == DOC_TYPE)
+        .map(|(_, b)| read_string(b));
+
+    let mut info = VideoInfo {
+        container: match doc_type.as_deref() {
+            Some("webm") => "WebM",
+            _ => "Matroska",
+        }
+        .into(),
+        duration: None,
+        title: None,
+        tracks: Vec::new(),
+    };
+
+    match read_header(&mut file)? {
+        Some((SEGMENT, _)) => (),
+        _ => return Err("no Matroska segment".into()),
+    }
+
+    // walk the segment's top-level elements until we have reached the media
+    let mut have_info = false;
+    let mut have_tracks = false;
+    while !(have_info && have_tracks) {
+        let (id, size) = match read_header(&mut file)? {
+            Some(h) => h,
+            None => break,
+        };
+        if id == CLUSTER || size == UNKNOWN_SIZE {
+            break;
+        }
+        match id {
+            INFO => {
+                read_info(&read_body(&mut file, size)?, &mut info)?;
+                have_info = true;
+            }
+            TRACKS => {
+                let tracks = read_body(&mut file, size)?;
+                info.tracks = elements(&tracks)
+                    .filter(|(id, _)| *id == TRACK_ENTRY)
+                    .map(|(_, b)| read_track(b))
+                    .collect();
+                have_tracks = true;
+            }
+            _ => {
+                file.seek(SeekFrom::Current(size as i64))?;
+            }
+        }
+    }
+
+    Ok(info)
+}
+
+fn read_info(data: &[u8], info: &mut VideoInfo) -> Result<(), ViewError> {
+    let mut scale = 1_000_000;
+    let mut duration = None;
+    for (id, body) in elements(data) {
+        match id {
+            TIMESTAMP_SCALE => scale = read_uint(body),
+            DURATION => duration = read_float(body),
+            TITLE => info.title = Some(read_string(body)),
+            _ => (),
+        }
+    }
+    info.duration = duration
+        .filter(|d| *d > 0.0)
+        .map(|d| seconds(d * scale as f64 / 1e9))
+        .transpose()?;
+    Ok(())
+}
+
+fn read_track(data: &[u8]) -> Track {
+    let mut track_type = 0;
+    let mut codec = String::new();
+    let mut language = None;
+    let mut bcp47 = None;
+    let mut name = None;
+    let mut frame_ns = None;
+    let mut width = 0;
+    let mut height = 0;
+    let mut sample_rate = None;
+    let mut channels = None;
+
+    for (id, body) in elements(data) {
+        match id {
+            TRACK_TYPE => track_type = read_uint(body),
+            CODEC_ID => codec = read_string(body),
+            LANGUAGE => language = Some(read_string(body)),
+            LANGUAGE_BCP47 => bcp47 = Some(read_string(body)),
+            NAME => name = Some(read_string(body)),
+            DEFAULT_DURATION => frame_ns = Some(read_uint(body)),
+            VIDEO => {
+                for (id, body) in elements(body) {
+                    match id {
+                        PIXEL_WIDTH => width = read_uint(body) as u32,
+                        PIXEL_HEIGHT => height = read_uint(body) as u32,
+                        _ => (),
+                    }
+                }
+            }
+            AUDIO => {
+                for (id, body) in elements(body) {
+                    match id {
+                        SAMPLING_FREQUENCY => sample_rate = read_float(body),
+                        CHANNELS => channels = Some(read_uint(body) as u32),
+                        _ => (),
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    let kind = match track_type {
+        1 => TrackKind::Video {
+            width,
+            height,
+            fps: frame_ns.filter(|ns| *ns > 0).map(|ns| 1e9 / ns as f64),
+        },
+        2 => TrackKind::Audio {
+            sample_rate,
+            channels,
+        },
+        0x11 => TrackKind::Subtitle,
+        0x10 => TrackKind::Other("logo".into()),
+        0x12 => TrackKind::Other("buttons".into()),
+        0x20 => TrackKind::Other("control".into()),
+        0x21 => TrackKind::Other("metadata".into()),
+        _ => TrackKind::Other("track".into()),
+    };
+
+    Track {
+        kind,
+        codec: codec_name(&codec),
+        language: bcp47.or(language).filter(|l| l != "und"),
+        name: name.filter(|n| !n.is_empty()),
+    }
+}
+
+/// Read an element header (ID and data size) from a stream.
+fn read_header<R: Read>(src: &mut R) -> Result<Option<(u32, u64)>, ViewError> {
+    let mut first = [0u8];
+    if src.read(&mut first)? == 0 {
+        return Ok(None);
+    }
+    let id_len = first[0].leading_zeros() as usize + 1;
+    if id_len > 4 {
+        return Err("invalid EBML element ID".into());
+    }
+    let mut id = first[0] as u32;
+    for _ in 1..id_len {
+        id = (id << 8) | read_byte(src)? as u32;
+    }
+
+    let first = read_byte(src)?;
+    let size_len = first.leading_zeros() as usize + 1;
+    if size_len > 8 {
+        return Err("invalid EBML element size".into());
+    }
+    let mut size = (first as u64) & (0xFF >> size_len);
+    let mut all_ones = size == (0xFF >> size_len);
+    for _ in 1..size_len {
+        let b = read_byte(src)?;
+        all_ones &= b == 0xFF;
+        size = (size << 8) | b as u64;
+    }
+    Ok(Some((id, if all_ones { UNKNOWN_SIZE } else { size })))
+}
+
+fn read_byte<R: Read>(src: &mut R) -> io::Result<u8> {
+    let mut buf = [0u8];
+    src.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_body<R: Read>(src: &mut R, size: u64) -> Result<Vec<u8>, ViewError> {
+    if size > MAX_ELEMENT {
+        return Err("Matroska header element too large".into());
+    }
+    let mut buf = vec![0; size as usize];
+    src.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Iterate over the elements in an in-memory buffer.
+fn elements(data: &[u8]) -> impl Iterator<Item = (u32, &[u8])> {
+    let mut cur = data;
+    std::iter::from_fn(move || {
+        let mut src = cur;
+        let (id, size) = read_header(&mut src).ok()??;
+        if size > src.len() as u64 {
+            return None;
+        }
+        let (body, rest) = src.split_at(size as usize);
+        cur = rest;
+        Some((id, body))
+    })
+}
+
+fn read_uint(data: &[u8]) -> u64 {
+    data.iter().take(8).fold(0, |acc, b| (acc << 8) | *b as u64)
+}
+
+fn read_float(data: &[u8]) -> Option<f64> {
+    match data.len() {
+        4 => Some(f32::from_be_bytes(data.try_into().ok()?) as f64),
+        8 => Some(f64::from_be_bytes(data.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+fn read_string(data: &[u8]) -> String {
+    String::from_utf8_lossy(data)
+        .trim_end_matches(char::from(0))
+        .to_string()
+}
+
+fn codec_name(id: &str) -> String {
+    let name = match id {
+        "V_MPEG4/ISO/AVC" => "H.264",
+        "V_MPEGH/ISO/HEVC" => "H.265",
+        "V_AV1" => "AV1",
+        "V_VP8" => "VP8",
+        "V_VP9" => "VP9",
+        "V_THEORA" => "Theora",
+        "V_MPEG2" => "MPEG-2",
+        "V_MJPEG" => "Motion JPEG",
+        "A_AAC" => "AAC",
+        "A_OPUS" => "Opus",
+        "A_VORBIS" => "Vorbis",
+        "A_FLAC" => "FLAC",
+        "A_AC3" => "AC-3",
+        "A_EAC3" => "E-AC-3",
+        "A_DTS" => "DTS",
+        "A_TRUEHD" => "TrueHD",
+        "A_MPEG/L3" => "MP3",
+        "S_TEXT/UTF8" => "SubRip",
+        "S_TEXT/ASS" | "S_TEXT/SSA" => "ASS/SSA",
+        "S_TEXT/WEBVTT" => "WebVTT",
+        "S_HDMV/PGS" => "PGS",
+        "S_VOBSUB" => "VobSub",
+        _ if id.starts_with("A_AAC/") => "AAC",
+        _ if id.starts_with("A_PCM/") => "PCM",
+        _ if id.starts_with("V_MPEG4/") => "MPEG-4 Part 2",
+        _ => id,
+    };
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    /// Encode an element with a one-byte size.
+    fn element(id: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut out = id.to_vec();
+        out.push(0x80 | body.len() as u8);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn webm(info: &[u8]) -> NamedTempFile {
+        let mut data = element(&[0x1A, 0x45, 0xDF, 0xA3], &element(&[0x42, 0x82], b"webm"));
+        // segment of unknown size
+        data.extend_from_slice(&[0x18, 0x53, 0x80, 0x67, 0xFF]);
+        data.extend(element(&[0x15, 0x49, 0xA9, 0x66], info));
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(&data).unwrap();
+        file
+    }
+
+    #[test]
+    fn reads_duration() {
+        let file = webm(&element(&[0x44, 0x89], &1500.0f64.to_be_bytes()));
+        let info = read_mkv(file.path()).unwrap();
+        assert_eq!(info.container, "WebM");
+        assert_eq!(info.duration, Some(Duration::from_millis(1500)));
+    }
+
+    #[test]
+    fn rejects_infinite_duration() {
+        let file = webm(&element(&[0x44, 0x89], &f64::INFINITY.to_be_bytes()));
+        assert!(read_mkv(file.path()).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let file = webm(&element(&[0x44, 0x89], &1500.0f64.to_be_bytes()));
+        let data = std::fs::read(file.path()).unwrap();
+        for len in 0..data.len() {
+            let mut short = NamedTempFile::new().unwrap();
+            short.write_all(&data[..len]).unwrap();
+            // must not panic; short headers are errors
+            let _ = read_mkv(short.path());
+        }
+    }
+}
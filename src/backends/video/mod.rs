@@ -0,0 +1,231 @@
+//! Video container metadata.
+//!
+//! MP4/QuickTime, Matroska/WebM and AVI containers are parsed natively; other
+//! formats are described with `ffprobe` when it is available.
+use std::fs::File;
+use std::io::Read;
+use std::time::Duration;
+
+use log::*;
+
+use crate::interface::*;
+use crate::mime::mime_db;
+use crate::programs::find_program;
+
+mod avi;
+mod ffprobe;
+mod mkv;
+mod mp4;
+
+/// MIME types for containers we can parse without external programs.
+const NATIVE_TYPES: &[&str] = &[
+    "video/mp4",
+    "video/quicktime",
+    "video/x-m4v",
+    "video/3gpp",
+    "video/3gpp2",
+    "video/x-matroska",
+    "video/webm",
+    "video/x-msvideo",
+];
+
+/// Video backend.
+pub struct VideoBackend;
+
+struct VideoMeta;
+
+/// Summary information about a video container.
+struct VideoInfo {
+    container: String,
+    duration: Option<Duration>,
+    title: Option<String>,
+    tracks: Vec<Track>,
+}
+
+/// A single track (stream) in a container.
+struct Track {
+    kind: TrackKind,
+    codec: String,
+    language: Option<String>,
+    name: Option<String>,
+}
+
+enum TrackKind {
+    Video {
+        width: u32,
+        height: u32,
+        fps: Option<f64>,
+    },
+    Audio {
+        sample_rate: Option<f64>,
+        channels: Option<u32>,
+    },
+    Subtitle,
+    Other(String),
+}
+
+impl FileViewer for VideoBackend {
+    fn name(&self) -> &'static str {
+        "video"
+    }
+
+    fn make_view(&self, req: &FileRequest, mode: &Option<ViewType>) -> Option<Box<dyn FileView>> {
+        let mode = mode.clone().unwrap_or(ViewType::Meta);
+        if mode != ViewType::Meta || !req.mime_type.starts_with("video/") {
+            return None;
+        }
+        let db = mime_db();
+        if NATIVE_TYPES
+            .iter()
+            .any(|t| db.is_subtype(&req.mime_type, t))
+        {
+            return Some(Box::new(VideoMeta));
+        }
+        match find_program("ffprobe") {
+            Ok(Some(_)) => Some(Box::new(VideoMeta)),
+            _ => None,
+        }
+    }
+}
+
+impl MetaView for VideoMeta {
+    fn describe(&self, req: &FileRequest, _options: &ViewOptions) -> Result<MetaInfo, ViewError> {
+        let video = match read_native(req) {
+            Ok(Some(video)) => video,
+            Ok(None) => {
+                info!("no native parser for {}", req.mime_type);
+                ffprobe::read_ffprobe(req)?
+            }
+            Err(e) => {
+                warn!("error parsing video container: {}", e);
+                ffprobe::read_ffprobe(req).map_err(|_| e)?
+            }
+        };
+        Ok(describe_video(req, &video))
+    }
+}
+
+/// Describe a parsed video container.
+fn describe_video(req: &FileRequest, video: &VideoInfo) -> MetaInfo {
+    let db = mime_db();
+    let mut info = MetaInfo::new(db.description(&req.mime_type).unwrap_or("Video file"));
+    info.summary = video
+        .duration
+        .map(|d| Field::new("duration", "Duration", Value::Duration(d)));
+    info.size = req.file_size();
+
+    info.add("container", "Container", video.container.as_str());
+    info.add_opt("title", "Title", video.title.as_ref());
+    let dims = video.tracks.iter().find_map(|t| match t.kind {
+        TrackKind::Video { width, height, fps } => Some((width, height, fps)),
+        _ => None,
+    });
+    if let Some((w, h, fps)) = dims {
+        info.add("resolution", "Resolution", Value::Dimensions(w, h));
+        info.add_opt(
+            "frame_rate",
+            "Frame rate",
+            fps.map(|f| Value::Quantity(f, "fps")),
+        );
+    }
+
+    let tracks = video
+        .tracks
+        .iter()
+        .enumerate()
+        .map(|(i, track)| Value::Text(format!("#{} {}", i + 1, track.describe())))
+        .collect();
+    info.add("tracks", "Tracks", Value::List(tracks));
+    info
+}
+
+impl Track {
+    fn describe(&self) -> String {
+        let mut desc = match &self.kind {
+            TrackKind::Video { width, height, fps } => {
+                let mut s = format!("video: {} {}x{}", self.codec, width, height);
+                if let Some(fps) = fps {
+                    s.push_str(&format!(" @ {:.3} fps", fps));
+                }
+                s
+            }
+            TrackKind::Audio {
+                sample_rate,
+                channels,
+            } => {
+                let mut s = format!("audio: {}", self.codec);
+                if let Some(rate) = sample_rate {
+                    s.push_str(&format!(" {} Hz", rate.round()));
+                }
+                match channels {
+                    Some(1) => s.push_str(", mono"),
+                    Some(2) => s.push_str(", stereo"),
+                    Some(n) => s.push_str(&format!(", {} channels", n)),
+                    None => (),
+                }
+                s
+            }
+            TrackKind::Subtitle => format!("subtitle: {}", self.codec),
+            TrackKind::Other(kind) => format!("{}: {}", kind, self.codec),
+        };
+        if let Some(lang) = &self.language {
+            desc.push_str(&format!(" [{}]", lang));
+        }
+        if let Some(name) = &self.name {
+            desc.push_str(&format!(" \"{}\"", name));
+        }
+        desc
+    }
+}
+
+/// Try to parse the container natively, returning `None` for unknown formats.
+fn read_native(req: &FileRequest) -> Result<Option<VideoInfo>, ViewError> {
+    let mut file = File::open(&req.path)?;
+    let mut magic = [0u8; 12];
+    let n = file.read(&mut magic)?;
+    let magic = &magic[..n];
+    drop(file);
+
+    if magic.get(4..8) == Some(b"ftyp") || magic.get(4..8) == Some(b"moov") {
+        debug!("parsing as MP4");
+        mp4::read_mp4(&req.path).map(Some)
+    } else if magic.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        debug!("parsing as Matroska");
+        mkv::read_mkv(&req.path).map(Some)
+    } else if magic.starts_with(b"RIFF") && magic.get(8..12) == Some(b"AVI ") {
+        debug!("parsing as AVI");
+        avi::read_avi(&req.path).map(Some)
+    } else {
+        Ok(None)
+    }
+}
+
+/// Convert a duration read from a file, rejecting invalid values.
+fn seconds(secs: f64) -> Result<Duration, ViewError> {
+    Duration::try_from_secs_f64(secs).map_err(ViewError::wrap)
+}
+
+fn be_u16(data: &[u8], off: usize) -> Option<u16> {
+    Some(u16::from_be_bytes(data.get(off..off + 2)?.try_into().ok()?))
+}
+
+fn be_u32(data: &[u8], off: usize) -> Option<u32> {
+    Some(u32::from_be_bytes(data.get(off..off + 4)?.try_into().ok()?))
+}
+
+fn be_u64(data: &[u8], off: usize) -> Option<u64> {
+    Some(u64::from_be_bytes(data.get(off..off + 8)?.try_into().ok()?))
+}
+
+fn le_u16(data: &[u8], off: usize) -> Option<u16> {
+    Some(u16::from_le_bytes(data.get(off..off + 2)?.try_into().ok()?))
+}
+
+fn le_u32(data: &[u8], off: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(data.get(off..off + 4)?.try_into().ok()?))
+}
+
+/// Render a four-character code for display.
+fn fourcc(code: &[u8]) -> String {
+    String::from_utf8_lossy(code).trim_end().to_string()
+}
@@ -0,0 +1,345 @@
+//! MP4 and QuickTime (ISO base media) containers.
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+use super::*;
+
+/// Largest `moov` box we are willing to load.
+const MAX_MOOV: u64 = 64 * 1024 * 1024;
+
+/// Iterator over the boxes in a buffer.
+struct Boxes<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Iterator for Boxes<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let size = be_u32(self.data, 0)? as u64;
+        let kind = self.data.get(4..8)?;
+        let (header, size) = match size {
+            0 => (8, self.data.len() as u64),
+            1 => (16, be_u64(self.data, 8)?),
+            n => (8, n),
+        };
+        if size < header as u64 || size > self.data.len() as u64 {
+            return None;
+        }
+        let body = &self.data[header..size as usize];
+        self.data = &self.data[size as usize..];
+        Some((kind, body))
+    }
+}
+
+fn boxes(data: &[u8]) -> Boxes<'_> {
+    Boxes { data }
+}
+
+fn find_box<'a>(data: &'a [u8], kind: &[u8]) -> Option<&'a [u8]> {
+    boxes(data).find(|(k, _)| *k == kind).map(|(_, b)| b)
+}
+
+pub(super) fn read_mp4(path: &Path) -> Result<VideoInfo, ViewError> {
+    let moov = load_moov(path)?.ok_or("no moov box in MP4 file")?;
+
+    let mut info = VideoInfo {
+        container: "MP4/QuickTime".into(),
+        duration: None,
+        title: None,
+        tracks: Vec::new(),
+    };
+    for (kind, body) in boxes(&moov) {
+        match kind {
+            b"mvhd" => info.duration = read_mvhd(body)?,
+            b"trak" => {
+                if let Some(track) = read_trak(body) {
+                    info.tracks.push(track);
+                }
+            }
+            _ => (),
+        }
+    }
+    Ok(info)
+}
+
+/// Find the `moov` box, skipping over (potentially very large) media data.
+fn load_moov(path: &Path) -> Result<Option<Vec<u8>>, ViewError> {
+    let mut file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut pos = 0;
+    while pos + 8 <= len {
+        file.seek(SeekFrom::Start(pos))?;
+        let mut header = [0u8; 16];
+        file.read_exact(&mut header[..8])?;
+        let mut size = be_u32(&header, 0).unwrap_or_default() as u64;
+        let mut hlen = 8;
+        if size == 1 {
+            file.read_exact(&mut header[8..])?;
+            size = be_u64(&header, 8).unwrap_or_default();
+            hlen = 16;
+        } else if size == 0 {
+            size = len - pos;
+        }
+        if size < hlen {
+            return Err("invalid MP4 box size".into());
+        }
+
+        if &header[4..8] == b"moov" {
+            let body = size - hlen;
+            if body > MAX_MOOV {
+                return Err("MP4 moov box too large".into());
+            }
+            let mut data = vec![0; body as usize];
+            file.read_exact(&mut data)?;
+            return Ok(Some(data));
+        }
+        pos = pos
+            .checked_add(size)
+            .filter(|p| *p <= len)
+            .ok_or("MP4 box extends past end of file")?;
+    }
+    Ok(None)
+}
+
+/// Read (timescale, duration) from a movie or media header.
+fn read_timing(body: &[u8], version_1_off: usize, version_0_off: usize) -> Option<(u32, u64)> {
+    if body.first() == Some(&1) {
+        Some((
+            be_u32(body, version_1_off)?,
+            be_u64(body, version_1_off + 4)?,
+        ))
+    } else {
+        Some((
+            be_u32(body, version_0_off)?,
+            be_u32(body, version_0_off + 4)? as u64,
+        ))
+    }
+}
+
+fn read_mvhd(body: &[u8]) -> Result<Option<Duration>, ViewError> {
+    match read_timing(body, 20, 12) {
+        Some((scale, dur)) if scale > 0 => seconds(dur as f64 / scale as f64).map(Some),
+        _ => Ok(None),
+    }
+}
+
+fn read_trak(trak: &[u8]) -> Option<Track> {
+    let tkhd = find_box(trak, b"tkhd")?;
+    // width and height are 16.16 fixed point at the end of the header
+    let dims_off = if tkhd.first() == Some(&1) { 88 } else { 76 };
+    let mut width = be_u32(tkhd, dims_off).unwrap_or_default() >> 16;
+    let mut height = be_u32(tkhd, dims_off + 4).unwrap_or_default() >> 16;
+
+    let mdia = find_box(trak, b"mdia")?;
+    let mdhd = find_box(mdia, b"mdhd")?;
+    let (scale, dur) = read_timing(mdhd, 20, 12)?;
+    let lang_off = if mdhd.first() == Some(&1) { 32 } else { 20 };
+    let language = be_u16(mdhd, lang_off).and_then(decode_language);
+    let hdlr = find_box(mdia, b"hdlr")?;
+    let handler = hdlr.get(8..12)?;
+    let name = hdlr
+        .get(24..)
+        .map(|n| {
+            String::from_utf8_lossy(n)
+                .trim_matches(char::from(0))
+                .trim()
+                .to_string()
+        })
+        // skip generic handler names written by common muxers
+        .filter(|n| !n.is_empty() && !n.ends_with("Handler") && !n.starts_with("Core Media"));
+
+    let stbl = find_box(find_box(mdia, b"minf")?, b"stbl")?;
+    let stsd = find_box(stbl, b"stsd")?;
+    // skip version/flags and entry count to get the first sample entry
+    let (codec, entry) = boxes(stsd.get(8..)?).next()?;
+
+    let kind = match handler {
+        b"vide" => {
+            if width == 0 || height == 0 {
+                width = be_u16(entry, 24).unwrap_or_default() as u32;
+                height = be_u16(entry, 26).unwrap_or_default() as u32;
+            }
+            let samples: u64 = find_box(stbl, b"stts")
+                .map(|stts| {
+                    // don't trust the entry count beyond the box's contents
+                    let n = be_u32(stts, 4).unwrap_or_default() as usize;
+                    let n = n.min(stts.len().saturating_sub(8) / 8);
+                    (0..n)
+                        .filter_map(|i| be_u32(stts, 8 + i * 8))
+                        .map(|c| c as u64)
+                        .sum()
+                })
+                .unwrap_or_default();
+            let fps = if dur > 0 && samples > 0 {
+                Some(samples as f64 * scale as f64 / dur as f64)
+            } else {
+                None
+            };
+            TrackKind::Video { width, height, fps }
+        }
+        b"soun" => TrackKind::Audio {
+            channels: be_u16(entry, 16).map(|c| c as u32),
+            sample_rate: be_u32(entry, 24).map(|r| (r >> 16) as f64),
+        },
+        b"sbtl" | b"subt" | b"text" | b"clcp" => TrackKind::Subtitle,
+        other => TrackKind::Other(fourcc(other)),
+    };
+
+    Some(Track {
+        kind,
+        codec: codec_name(codec),
+        language,
+        name,
+    })
+}
+
+/// Decode a packed ISO 639-2 language code.
+fn decode_language(code: u16) -> Option<String> {
+    let chars: String = [(code >> 10) & 0x1F, (code >> 5) & 0x1F, code & 0x1F]
+        .iter()
+        .map(|c| char::from(*c as u8 + 0x60))
+        .collect();
+    if chars == "und" || !chars.chars().all(|c| c.is_ascii_lowercase()) {
+        None
+    } else {
+        Some(chars)
+    }
+}
+
+fn codec_name(code: &[u8]) -> String {
+    match code {
+        b"avc1" | b"avc3" => "H.264".into(),
+        b"hev1" | b"hvc1" => "H.265".into(),
+        b"av01" => "AV1".into(),
+        b"vp08" => "VP8".into(),
+        b"vp09" => "VP9".into(),
+        b"mp4v" => "MPEG-4 Part 2".into(),
+        b"apch" | b"apcn" | b"apcs" | b"apco" | b"ap4h" => "ProRes".into(),
+        b"mp4a" => "AAC".into(),
+        b"ac-3" => "AC-3".into(),
+        b"ec-3" => "E-AC-3".into(),
+        b"Opus" => "Opus".into(),
+        b"fLaC" => "FLAC".into(),
+        b"alac" => "ALAC".into(),
+        b"lpcm" | b"sowt" | b"twos" => "PCM".into(),
+        b"tx3g" => "3GPP timed text".into(),
+        b"wvtt" => "WebVTT".into(),
+        b"c608" => "CEA-608".into(),
+        other => fourcc(other),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    fn mp4_box(kind: &[u8], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out
+    }
+
+    /// A version 0 movie header with the given timescale and duration.
+    fn mvhd(scale: u32, duration: u32) -> Vec<u8> {
+        let mut body = vec![0; 12];
+        body.extend_from_slice(&scale.to_be_bytes());
+        body.extend_from_slice(&duration.to_be_bytes());
+        body.resize(100, 0);
+        mp4_box(b"mvhd", &body)
+    }
+
+    fn write(data: &[u8]) -> NamedTempFile {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(data).unwrap();
+        file
+    }
+
+    #[test]
+    fn reads_duration() {
+        let mut data = mp4_box(b"ftyp", b"isom");
+        data.extend(mp4_box(b"moov", &mvhd(1000, 2500)));
+        let info = read_mp4(write(&data).path()).unwrap();
+        assert_eq!(info.duration, Some(Duration::from_millis(2500)));
+    }
+
+    #[test]
+    fn rejects_huge_duration() {
+        let mut body = vec![1, 0, 0, 0];
+        body.resize(20, 0);
+        body.extend_from_slice(&1u32.to_be_bytes());
+        body.extend_from_slice(&u64::MAX.to_be_bytes());
+        body.resize(112, 0);
+        let mut data = mp4_box(b"ftyp", b"isom");
+        data.extend(mp4_box(b"moov", &mp4_box(b"mvhd", &body)));
+        assert!(read_mp4(write(&data).path()).is_err());
+    }
+
+    #[test]
+    fn rejects_overflowing_box_size() {
+        let mut data = mp4_box(b"ftyp", b"isom");
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&(u64::MAX - 4).to_be_bytes());
+        data.extend(mp4_box(b"moov", &mvhd(1000, 2500)));
+        assert!(read_mp4(write(&data).path()).is_err());
+    }
+
+    #[test]
+    fn rejects_truncated_file() {
+        let mut data = mp4_box(b"ftyp", b"isom");
+        data.extend(mp4_box(b"moov", &mvhd(1000, 2500)));
+        for len in 0..data.len() {
+            // must not panic
+            let _ = read_mp4(write(&data[..len]).path());
+        }
+    }
+
+    #[test]
+    fn caps_sample_count_entries() {
+        let mut stts = vec![0; 4];
+        stts.extend_from_slice(&u32::MAX.to_be_bytes());
+        stts.extend_from_slice(&[0, 0, 0, 10, 0, 0, 0, 1]);
+        let stbl = [
+            mp4_box(
+                b"stsd",
+                &[&[0u8; 8][..], &mp4_box(b"avc1", &[0; 80])].concat(),
+            ),
+            mp4_box(b"stts", &stts),
+        ]
+        .concat();
+        let mut tkhd = vec![0; 76];
+        tkhd.extend_from_slice(&(640u32 << 16).to_be_bytes());
+        tkhd.extend_from_slice(&(480u32 << 16).to_be_bytes());
+        let mut mdhd = vec![0; 12];
+        mdhd.extend_from_slice(&10u32.to_be_bytes());
+        mdhd.extend_from_slice(&1u32.to_be_bytes());
+        mdhd.resize(24, 0);
+        let mut hdlr = vec![0; 8];
+        hdlr.extend_from_slice(b"vide");
+        hdlr.resize(24, 0);
+        let mdia = [
+            mp4_box(b"mdhd", &mdhd),
+            mp4_box(b"hdlr", &hdlr),
+            mp4_box(b"minf", &mp4_box(b"stbl", &stbl)),
+        ]
+        .concat();
+        let trak = [mp4_box(b"tkhd", &tkhd), mp4_box(b"mdia", &mdia)].concat();
+
+        let track = read_trak(&trak).unwrap();
+        match track.kind {
+            TrackKind::Video { width, height, fps } => {
+                assert_eq!((width, height), (640, 480));
+                assert_eq!(fps, Some(100.0));
+            }
+            _ => panic!("expected a video track"),
+        }
+    }
+}
@@ -0,0 +1,230 @@
+//! User configuration file.
+//!
+//! The configuration is read from `autoview/config.toml` in the XDG config
+//! directory (`$XDG_CONFIG_HOME`, defaulting to `~/.config`).
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs::read_to_string;
+use std::io;
+use std::path::PathBuf;
+
+use log::*;
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::interface::{HashAlgorithm, OutputFormat, SortKey, ViewSpeed, ViewType};
+use crate::mime::mime_db;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("cannot read {0}: {1}")]
+    IO(PathBuf, io::Error),
+    #[error("invalid configuration in {0}: {1}")]
+    Parse(PathBuf, toml::de::Error),
+}
+
+/// AutoView configuration.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    /// Default values for command-line flags.
+    pub defaults: Defaults,
+    /// Pager command, used instead of `$PAGER`.
+    pub pager: Option<String>,
+    /// Preferred external directory listers, tried before the others.
+    pub listers: Vec<String>,
+    /// Syntax highlighting theme for source text.
+    pub theme: Option<String>,
+    /// Preferences for MIME types, keyed by type.  A type's settings also
+    /// apply to its subtypes.
+    pub types: BTreeMap<String, TypePrefs>,
+    /// User-defined external viewers, tried before the built-in backends.
+    pub viewers: Vec<ViewerConfig>,
+}
+
+/// Defaults for command-line flags.  Flags given on the command line take
+/// precedence.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Defaults {
+    pub long: bool,
+    pub all: bool,
+    pub git_ignore: bool,
+    pub tree: bool,
+    pub icons: bool,
+    pub raw: bool,
+    pub native_ls: bool,
+    pub speed: Option<ViewSpeed>,
+    pub sort: Option<SortKey>,
+    pub format: Option<OutputFormat>,
+    pub hash: Vec<HashAlgorithm>,
+}
+
+/// Preferences for a MIME type.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TypePrefs {
+    /// Default view for files of this type.
+    pub view: Option<ViewType>,
+    /// Backend to view files of this type.
+    pub backend: Option<String>,
+}
+
+/// A user-defined external viewer.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ViewerConfig {
+    /// Name for selecting the viewer with `--backend`.
+    pub name: String,
+    /// MIME type to view.  Subtypes also match, and `type/*` matches every
+    /// type in the family.
+    #[serde(default)]
+    pub mime: Option<String>,
+    /// Glob for names of files to view.
+    #[serde(default)]
+    pub glob: Option<String>,
+    /// Command for the Meta view, split into words like a shell command.
+    /// `{}` in each word is replaced with the file path, which is appended if
+    /// there is no `{}`.
+    #[serde(default)]
+    pub meta: Option<String>,
+    /// Command for the Head view.
+    #[serde(default)]
+    pub head: Option<String>,
+    /// Command for the Full view.
+    #[serde(default)]
+    pub full: Option<String>,
+    /// Whether to page the output of the Full view.
+    #[serde(default)]
+    pub paged: bool,
+}
+
+impl Config {
+    /// Get the path to the configuration file.
+    pub fn path() -> Option<PathBuf> {
+        let dir = match env::var_os("XDG_CONFIG_HOME") {
+            Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+            _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+        };
+        Some(dir.join("autoview").join("config.toml"))
+    }
+
+    /// Load the configuration, returning the default configuration if there
+    /// is no configuration file.
+    pub fn load() -> Result<Config, ConfigError> {
+        let path = match Config::path() {
+            Some(p) => p,
+            None => {
+                debug!("no home directory, using default configuration");
+                return Ok(Config::default());
+            }
+        };
+        let text = match read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                debug!("{} not found, using default configuration", path.display());
+                return Ok(Config::default());
+            }
+            Err(e) => return Err(ConfigError::IO(path, e)),
+        };
+        info!("loading configuration from {}", path.display());
+        toml::from_str(&text).map_err(|e| ConfigError::Parse(path, e))
+    }
+
+    /// Get the preferences for a MIME type, using the most specific of the
+    /// type and its supertypes that has preferences.
+    pub fn type_prefs(&self, mime_type: &str) -> Option<&TypePrefs> {
+        let db = mime_db();
+        self.types
+            .iter()
+            .filter(|(key, _)| db.is_subtype(mime_type, key))
+            .max_by(|(a, _), (b, _)| {
+                if db.is_subtype(a, b) {
+                    Ordering::Greater
+                } else if db.is_subtype(b, a) {
+                    Ordering::Less
+                } else {
+                    Ordering::Equal
+                }
+            })
+            .map(|(_, prefs)| prefs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Get the example configuration from the README.
+    fn readme_example() -> &'static str {
+        let readme = include_str!("../README.md");
+        let section = &readme[readme.find("## Configuration").unwrap()..];
+        let start = section.find("```toml\n").unwrap() + "```toml\n".len();
+        let len = section[start..].find("```").unwrap();
+        &section[start..start + len]
+    }
+
+    #[test]
+    fn parses_example() {
+        let config: Config = toml::from_str(readme_example()).unwrap();
+        assert_eq!(config.pager.as_deref(), Some("less -R"));
+        assert_eq!(config.listers, ["lsd", "eza"]);
+        let defaults = &config.defaults;
+        assert!(defaults.long && defaults.native_ls && !defaults.all);
+        assert_eq!(defaults.sort, Some(SortKey::Size));
+        assert_eq!(defaults.speed, None);
+        assert_eq!(
+            defaults.hash,
+            [HashAlgorithm::Sha256, HashAlgorithm::Blake3]
+        );
+        let log = &config.types["text/x-log"];
+        assert_eq!(log.view, Some(ViewType::Full));
+        assert_eq!(log.backend.as_deref(), Some("text"));
+        assert_eq!(config.viewers[0].name, "jq");
+        assert!(config.viewers[0].paged);
+        assert!(config.viewers[0].meta.is_none());
+    }
+
+    #[test]
+    fn empty_config() {
+        let config: Config = toml::from_str("").unwrap();
+        assert!(config.pager.is_none());
+        assert!(config.types.is_empty());
+        assert!(config.viewers.is_empty());
+        assert!(!config.defaults.long);
+    }
+
+    #[test]
+    fn rejects_invalid_config() {
+        assert!(toml::from_str::<Config>("paper = \"less\"").is_err());
+        assert!(toml::from_str::<Config>("[defaults]\nsort = \"color\"").is_err());
+        assert!(toml::from_str::<Config>("[types.\"text/plain\"]\nview = \"all\"").is_err());
+        assert!(toml::from_str::<Config>("[[viewers]]\nmime = \"text/csv\"").is_err());
+    }
+
+    #[test]
+    fn prefers_specific_types() {
+        let config: Config = toml::from_str(
+            r#"
+            [types."application/octet-stream"]
+            backend = "binary"
+            [types."text/plain"]
+            view = "head"
+            [types."text/x-csrc"]
+            view = "full"
+            "#,
+        )
+        .unwrap();
+        let view = |t| config.type_prefs(t).and_then(|p| p.view.clone());
+        assert_eq!(view("text/x-csrc"), Some(ViewType::Full));
+        // C headers are a subtype of C source
+        assert_eq!(view("text/x-chdr"), Some(ViewType::Full));
+        assert_eq!(view("text/csv"), Some(ViewType::Head));
+        assert_eq!(view("text/plain"), Some(ViewType::Head));
+        let prefs = config.type_prefs("application/zip").unwrap();
+        assert_eq!(prefs.backend.as_deref(), Some("binary"));
+        let config = Config::default();
+        assert!(config.type_prefs("text/plain").is_none());
+    }
+}
@@ -0,0 +1,204 @@
+//! Explain type detection and backend selection.
+use std::path::Path;
+
+use shared_mime::MimeDB;
+
+use crate::backends::{backends, selected_backends};
+use crate::guess_type;
+use crate::interface::*;
+use crate::mime::mime_db;
+use crate::styling::{EXTRA_MARKER, FILE_TYPE};
+
+/// Explain how a file's type was detected and which backend will view it.
+pub fn explain_request(
+    req: &FileRequest,
+    view: &Option<ViewType>,
+    options: &ViewOptions,
+) -> Result<ViewOutput, ViewError> {
+    let db = mime_db();
+    let mut fields = vec![
+        Field::new("path", "Path", req.path.display().to_string()),
+        Field::new(
+            "mime_type",
+            "MIME type",
+            Value::Styled(req.mime_type.clone(), &FILE_TYPE),
+        ),
+    ];
+    // backends are chosen by the target's type unless the link is dangling
+    if let Some(target) = &req.link_target {
+        if req.is_dangling() {
+            fields.push(Field::new(
+                "type_source",
+                "Type of",
+                "the link itself (target missing)",
+            ));
+        } else {
+            fields.push(Field::new(
+                "type_source",
+                "Type of",
+                format!("link target {}", target.display()),
+            ));
+            fields.push(Field::new(
+                "link_type",
+                "Link type",
+                Value::Styled("inode/symlink".into(), &FILE_TYPE),
+            ));
+        }
+    }
+    fields.extend(type_evidence(&db, req));
+    fields.push(Field::new(
+        "view",
+        "View",
+        match view {
+            Some(v) => format!("{:?}", v),
+            None => "default".into(),
+        },
+    ));
+
+    let selected: Vec<_> = selected_backends(options)?.map(|b| b.name()).collect();
+    let mut chosen = None;
+    let rows = backends(options)
+        .map(|back| {
+            let result = match back.make_view(req, view) {
+                _ if !selected.contains(&back.name()) => "skipped",
+                Some(_) if chosen.is_none() => {
+                    chosen = Some(back.name());
+                    "selected"
+                }
+                Some(_) => "accepts",
+                None => "declines",
+            };
+            vec![Value::Text(back.name().into()), Value::Text(result.into())]
+        })
+        .collect();
+    fields.push(Field::new(
+        "backend",
+        "Backend",
+        match chosen {
+            Some(name) => Value::Text(name.into()),
+            None => Value::Styled("none".into(), &EXTRA_MARKER),
+        },
+    ));
+
+    let table = Table {
+        columns: vec![
+            Column::new("Backend", Align::Left),
+            Column::new("Result", Align::Left),
+        ],
+        rows,
+        headings: true,
+    };
+    Ok(ViewOutput::new(vec![
+        Block::Fields(fields),
+        Block::Section("Backends".into(), vec![Block::Table(table)]),
+    ]))
+}
+
+/// Reconstruct the evidence for the detected type, following the order the
+/// MIME database checks it.
+fn type_evidence(db: &MimeDB, req: &FileRequest) -> Vec<Field> {
+    let mut fields = Vec::new();
+    let detected = guess_type(db, &req.path).ok();
+    let detected = detected.as_ref().and_then(|a| a.best());
+    let evidence = match &req.meta {
+        _ if detected.is_some_and(|t| t != req.mime_type) => format!(
+            "overridden with --type (detected {})",
+            detected.unwrap_or_default()
+        ),
+        None => "metadata of the link itself (dangling link)".to_string(),
+        Some(meta) if db.query_meta(meta).best() != Some("application/octet-stream") => {
+            "file metadata (special file)".to_string()
+        }
+        Some(meta) => {
+            // links are detected from the name of their target
+            let resolved = match &req.link_target {
+                Some(_) => req.path.canonicalize().unwrap_or(req.path.clone()),
+                None => req.path.clone(),
+            };
+            let name = resolved.file_name().unwrap_or(resolved.as_os_str());
+            let ans = db.query_filename(name);
+            if !ans.is_unknown() {
+                let candidates: Vec<Value> = ans.all_types().iter().map(|t| (*t).into()).collect();
+                fields.push(Field::new(
+                    "glob_matches",
+                    "Glob matches",
+                    Value::List(candidates),
+                ));
+                if ans.is_ambiguous() {
+                    "file name glob (ambiguous)".to_string()
+                } else {
+                    format!("file name glob on {}", Path::new(name).display())
+                }
+            } else if meta.len() == 0 {
+                "empty file".to_string()
+            } else {
+                "no match (default type)".to_string()
+            }
+        }
+    };
+    fields.insert(0, Field::new("evidence", "Evidence", evidence));
+    fields.push(Field::new(
+        "magic",
+        "Magic",
+        Value::Styled(
+            "not checked (the MIME database only matches names)".into(),
+            &EXTRA_MARKER,
+        ),
+    ));
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+    use std::os::unix::fs::symlink;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::file_request;
+
+    fn field_text(output: &ViewOutput, label: &str) -> Option<String> {
+        match &output.blocks[0] {
+            Block::Fields(fields) => {
+                fields
+                    .iter()
+                    .find(|f| f.label == label)
+                    .map(|f| match &f.value {
+                        Value::Text(s) | Value::Styled(s, _) => s.clone(),
+                        _ => panic!("unexpected value for {}", label),
+                    })
+            }
+            _ => panic!("expected fields"),
+        }
+    }
+
+    #[test]
+    fn explains_link_types() {
+        let dir = TempDir::new().unwrap();
+        write(dir.path().join("notes.txt"), "hello\n").unwrap();
+        let link = dir.path().join("link");
+        symlink("notes.txt", &link).unwrap();
+        let broken = dir.path().join("broken");
+        symlink("missing.txt", &broken).unwrap();
+        let options = ViewOptions::default();
+
+        let req = file_request(&link).unwrap();
+        let output = explain_request(&req, &None, &options).unwrap();
+        assert_eq!(field_text(&output, "MIME type").unwrap(), "text/plain");
+        assert_eq!(
+            field_text(&output, "Type of").unwrap(),
+            "link target notes.txt"
+        );
+        assert_eq!(field_text(&output, "Link type").unwrap(), "inode/symlink");
+
+        let req = file_request(&broken).unwrap();
+        let output = explain_request(&req, &None, &options).unwrap();
+        assert_eq!(field_text(&output, "MIME type").unwrap(), "inode/symlink");
+        assert_eq!(
+            field_text(&output, "Type of").unwrap(),
+            "the link itself (target missing)"
+        );
+        assert_eq!(field_text(&output, "Link type"), None);
+    }
+}
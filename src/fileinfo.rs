@@ -0,0 +1,261 @@
+//! File system metadata helpers.
+use std::ffi::OsString;
+use std::fs::{symlink_metadata, Metadata};
+use std::os::unix::fs::{FileTypeExt, MetadataExt, PermissionsExt};
+
+use chrono::{DateTime, Local};
+use log::*;
+use nix::unistd::{Gid, Group, Uid, User};
+
+use crate::git::git_fields;
+use crate::hashing::hash_file;
+use crate::interface::{Field, FileRequest, Value, ViewOptions, ViewSpeed};
+use crate::links::link_fields;
+use crate::styling::EXTRA_MARKER;
+
+/// Largest file to checksum unless slow operations are allowed.
+const HASH_LIMIT: u64 = 64 * 1024 * 1024;
+const TIME_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+const ACL_ACCESS: &str = "system.posix_acl_access";
+const ACL_DEFAULT: &str = "system.posix_acl_default";
+
+/// Format a file's type and permissions like `ls -l`.
+pub fn mode_string(meta: &Metadata) -> String {
+    let mode = meta.permissions().mode();
+    let ft = meta.file_type();
+    let kind = if ft.is_dir() {
+        'd'
+    } else if ft.is_symlink() {
+        'l'
+    } else if ft.is_file() {
+        '-'
+    } else if ft.is_fifo() {
+        'p'
+    } else if ft.is_socket() {
+        's'
+    } else if ft.is_char_device() {
+        'c'
+    } else if ft.is_block_device() {
+        'b'
+    } else {
+        '?'
+    };
+    let mut out = String::from(kind);
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        out.push(if bits & 4 != 0 { 'r' } else { '-' });
+        out.push(if bits & 2 != 0 { 'w' } else { '-' });
+        out.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    out
+}
+
+/// Get the metadata fields shared by all Meta views, followed by link and git
+/// information.
+pub fn file_meta_fields(req: &FileRequest, options: &ViewOptions) -> Vec<Field> {
+    let mut fields = Vec::new();
+    match &req.meta {
+        Some(meta) => {
+            inode_fields(meta, &mut fields);
+            if meta.is_file() {
+                checksum_fields(req, meta, options, &mut fields);
+            }
+            if options.long_display {
+                extended_fields(req, meta, &mut fields);
+            }
+        }
+        // a dangling link still has metadata of its own
+        None => match symlink_metadata(&req.path) {
+            Ok(meta) => inode_fields(&meta, &mut fields),
+            Err(e) => debug!("{}: no link metadata: {}", req.path.display(), e),
+        },
+    }
+    fields.extend(link_fields(req));
+    fields.extend(git_fields(req, options));
+    fields
+}
+
+/// Get permissions, ownership, timestamps, and inode information.
+fn inode_fields(meta: &Metadata, fields: &mut Vec<Field>) {
+    fields.push(Field::new(
+        "permissions",
+        "Permissions",
+        format!("{} ({:04o})", mode_string(meta), meta.mode() & 0o7777),
+    ));
+    fields.push(Field::new(
+        "owner",
+        "Owner",
+        format!("{}:{}", user_name(meta.uid()), group_name(meta.gid())),
+    ));
+    fields.push(Field::new(
+        "modified",
+        "Modified",
+        format_time(meta.mtime(), meta.mtime_nsec()),
+    ));
+    fields.push(Field::new(
+        "accessed",
+        "Accessed",
+        format_time(meta.atime(), meta.atime_nsec()),
+    ));
+    fields.push(Field::new(
+        "changed",
+        "Changed",
+        format_time(meta.ctime(), meta.ctime_nsec()),
+    ));
+    fields.push(Field::new("inode", "Inode", meta.ino()));
+    fields.push(Field::new("links", "Links", meta.nlink()));
+}
+
+/// Get the requested file checksums.  Large files are only hashed with
+/// `--slow`.
+fn checksum_fields(
+    req: &FileRequest,
+    meta: &Metadata,
+    options: &ViewOptions,
+    fields: &mut Vec<Field>,
+) {
+    if options.hashes.is_empty() {
+        return;
+    }
+    if options.speed != ViewSpeed::Slow && meta.len() > HASH_LIMIT {
+        fields.push(Field::new(
+            "checksums",
+            "Checksums",
+            Value::Styled(
+                format!(
+                    "skipped for files over {} (use --slow)",
+                    friendly::bytes(HASH_LIMIT)
+                ),
+                &EXTRA_MARKER,
+            ),
+        ));
+        return;
+    }
+    match hash_file(&req.path, &options.hashes) {
+        Ok(hashes) => {
+            for (algo, hash) in hashes {
+                fields.push(Field::new(algo.key(), algo.name(), hash));
+            }
+        }
+        Err(e) => warn!("{}: cannot compute checksums: {}", req.path.display(), e),
+    }
+}
+
+/// Get birth time, extended attributes, and ACLs.
+fn extended_fields(req: &FileRequest, meta: &Metadata, fields: &mut Vec<Field>) {
+    match meta.created() {
+        Ok(time) => {
+            let time: DateTime<Local> = time.into();
+            fields.push(Field::new(
+                "created",
+                "Created",
+                time.format(TIME_FORMAT).to_string(),
+            ));
+        }
+        Err(e) => debug!("{}: no birth time: {}", req.path.display(), e),
+    }
+
+    for (key, label, attr) in [
+        ("access_acl", "Access ACL", ACL_ACCESS),
+        ("default_acl", "Default ACL", ACL_DEFAULT),
+    ] {
+        match xattr::get_deref(&req.path, attr) {
+            Ok(Some(acl)) => fields.push(Field::new(key, label, Value::List(decode_acl(&acl)))),
+            Ok(None) => (),
+            Err(e) => debug!("{}: cannot read {}: {}", req.path.display(), attr, e),
+        }
+    }
+
+    let attrs: Vec<OsString> = match xattr::list_deref(&req.path) {
+        Ok(attrs) => attrs
+            .filter(|a| a != ACL_ACCESS && a != ACL_DEFAULT)
+            .collect(),
+        Err(e) => {
+            debug!("{}: cannot list xattrs: {}", req.path.display(), e);
+            return;
+        }
+    };
+    if attrs.is_empty() {
+        return;
+    }
+    let values = attrs
+        .iter()
+        .map(|attr| {
+            let value = match xattr::get_deref(&req.path, attr) {
+                Ok(Some(v)) => format_xattr(&v),
+                Ok(None) => "".into(),
+                Err(e) => Value::Styled(e.to_string(), &EXTRA_MARKER),
+            };
+            Field::from_label(attr.to_string_lossy(), value)
+        })
+        .collect();
+    fields.push(Field::new(
+        "extended_attributes",
+        "Extended attributes",
+        Value::Record(values),
+    ));
+}
+
+fn format_time(secs: i64, nsecs: i64) -> String {
+    match DateTime::from_timestamp(secs, nsecs as u32) {
+        Some(time) => time.with_timezone(&Local).format(TIME_FORMAT).to_string(),
+        None => "invalid time".into(),
+    }
+}
+
+fn user_name(uid: u32) -> String {
+    match User::from_uid(Uid::from_raw(uid)) {
+        Ok(Some(user)) => user.name,
+        _ => uid.to_string(),
+    }
+}
+
+fn group_name(gid: u32) -> String {
+    match Group::from_gid(Gid::from_raw(gid)) {
+        Ok(Some(group)) => group.name,
+        _ => gid.to_string(),
+    }
+}
+
+/// Format an extended attribute value as text if possible.
+fn format_xattr(value: &[u8]) -> Value {
+    match std::str::from_utf8(value) {
+        Ok(s) if !s.chars().any(|c| c.is_control() && c != '\0') => s.trim_end_matches('\0').into(),
+        _ => Value::Styled(format!("({} bytes)", value.len()), &EXTRA_MARKER),
+    }
+}
+
+/// Decode a POSIX ACL extended attribute into `getfacl`-style entries.
+fn decode_acl(data: &[u8]) -> Vec<Value> {
+    // 4-byte version header, then 8-byte entries of tag, permissions, and id
+    if data.len() < 4 || u32::from_le_bytes([data[0], data[1], data[2], data[3]]) != 2 {
+        return vec![Value::Styled(
+            "unrecognized ACL format".into(),
+            &EXTRA_MARKER,
+        )];
+    }
+    data[4..]
+        .chunks_exact(8)
+        .map(|e| {
+            let tag = u16::from_le_bytes([e[0], e[1]]);
+            let perm = u16::from_le_bytes([e[2], e[3]]);
+            let id = u32::from_le_bytes([e[4], e[5], e[6], e[7]]);
+            let perms = format!(
+                "{}{}{}",
+                if perm & 4 != 0 { 'r' } else { '-' },
+                if perm & 2 != 0 { 'w' } else { '-' },
+                if perm & 1 != 0 { 'x' } else { '-' }
+            );
+            let entry = match tag {
+                0x01 => format!("user::{}", perms),
+                0x02 => format!("user:{}:{}", user_name(id), perms),
+                0x04 => format!("group::{}", perms),
+                0x08 => format!("group:{}:{}", group_name(id), perms),
+                0x10 => format!("mask::{}", perms),
+                0x20 => format!("other::{}", perms),
+                _ => format!("unknown tag {:#x}:{}:{}", tag, id, perms),
+            };
+            Value::Text(entry)
+        })
+        .collect()
+}
@@ -0,0 +1,365 @@
+//! Git repository information for files and directories.
+//!
+//! This reads repositories directly with `libgit2`, so it does not need the
+//! `git` program to be installed.
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use anstyle::Style;
+use chrono::{DateTime, Local};
+use git2::{Oid, Repository, Sort, Status, StatusOptions};
+use log::*;
+
+use crate::interface::{Field, FileRequest, ViewOptions, ViewSpeed};
+use crate::styling::{styled, GIT_IGNORED, GIT_MODIFIED, GIT_NEW};
+
+/// Maximum number of commits to search for a file's last change, unless
+/// slow operations are allowed.
+const MAX_HISTORY: usize = 5000;
+
+/// Simplified status of a path in the working tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum GitStatus {
+    Ignored,
+    Untracked,
+    Added,
+    Modified,
+    Conflicted,
+}
+
+/// The statuses of the entries under a directory.
+pub struct DirStatus {
+    /// Canonical path of the directory.
+    dir: PathBuf,
+    /// Statuses, keyed by path relative to the directory.  Directories get
+    /// the most significant status of their contents.
+    statuses: HashMap<PathBuf, GitStatus>,
+}
+
+/// Information about a commit.
+pub struct CommitInfo {
+    pub id: String,
+    pub summary: String,
+    pub author: String,
+    pub time: DateTime<Local>,
+}
+
+/// Open the repository containing a path, if there is one.
+pub fn open_repo(path: &Path) -> Option<Repository> {
+    match Repository::discover(path) {
+        Ok(repo) if !repo.is_bare() => Some(repo),
+        Ok(_) => None,
+        Err(e) => {
+            debug!("{}: no git repository: {}", path.display(), e.message());
+            None
+        }
+    }
+}
+
+/// Get a path relative to the repository working directory.
+fn repo_path(repo: &Repository, path: &Path) -> Option<(PathBuf, PathBuf)> {
+    let workdir = repo.workdir()?.canonicalize().ok()?;
+    let path = path.canonicalize().ok()?;
+    let rel = path.strip_prefix(&workdir).ok()?.to_path_buf();
+    Some((path, rel))
+}
+
+impl GitStatus {
+    fn from_status(status: Status) -> Option<GitStatus> {
+        if status.is_conflicted() {
+            Some(GitStatus::Conflicted)
+        } else if status.intersects(
+            Status::WT_MODIFIED
+                | Status::WT_DELETED
+                | Status::WT_RENAMED
+                | Status::WT_TYPECHANGE
+                | Status::INDEX_MODIFIED
+                | Status::INDEX_DELETED
+                | Status::INDEX_RENAMED
+                | Status::INDEX_TYPECHANGE,
+        ) {
+            Some(GitStatus::Modified)
+        } else if status.is_index_new() {
+            Some(GitStatus::Added)
+        } else if status.is_wt_new() {
+            Some(GitStatus::Untracked)
+        } else if status.is_ignored() {
+            Some(GitStatus::Ignored)
+        } else {
+            None
+        }
+    }
+
+    /// Single-character status code, as in `git status --short`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            GitStatus::Ignored => "!",
+            GitStatus::Untracked => "?",
+            GitStatus::Added => "A",
+            GitStatus::Modified => "M",
+            GitStatus::Conflicted => "U",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            GitStatus::Ignored => "ignored",
+            GitStatus::Untracked => "untracked",
+            GitStatus::Added => "added",
+            GitStatus::Modified => "modified",
+            GitStatus::Conflicted => "conflicted",
+        }
+    }
+
+    fn style(&self) -> &'static Style {
+        match self {
+            GitStatus::Ignored => &GIT_IGNORED,
+            GitStatus::Untracked | GitStatus::Added => &GIT_NEW,
+            GitStatus::Modified | GitStatus::Conflicted => &GIT_MODIFIED,
+        }
+    }
+
+    /// Render an (optional) status code with styling.
+    pub fn styled_code(status: Option<GitStatus>) -> String {
+        match status {
+            Some(s) => styled(s.code(), s.style()).to_string(),
+            None => " ".into(),
+        }
+    }
+}
+
+impl DirStatus {
+    /// Load the statuses of everything under a directory.
+    pub fn load(repo: &Repository, dir: &Path) -> Result<DirStatus, git2::Error> {
+        let (dir, rel) = repo_path(repo, dir)
+            .ok_or_else(|| git2::Error::from_str("directory is outside the working tree"))?;
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true)
+            .include_ignored(true)
+            .recurse_untracked_dirs(false)
+            .recurse_ignored_dirs(false);
+        if !rel.as_os_str().is_empty() {
+            opts.pathspec(&rel);
+        }
+
+        let mut statuses = HashMap::new();
+        for entry in repo.statuses(Some(&mut opts))?.iter() {
+            let status = match GitStatus::from_status(entry.status()) {
+                Some(s) => s,
+                None => continue,
+            };
+            let path = match entry.path().map(Path::new) {
+                Some(p) => p,
+                None => continue,
+            };
+            let path = match path.strip_prefix(&rel) {
+                Ok(p) => p,
+                Err(_) => continue,
+            };
+            // propagate to the containing directories (but a directory is
+            // not ignored just because it contains ignored files)
+            for ancestor in path.ancestors() {
+                if ancestor.as_os_str().is_empty()
+                    || (ancestor != path && status == GitStatus::Ignored)
+                {
+                    break;
+                }
+                let cur = statuses.entry(ancestor.to_path_buf()).or_insert(status);
+                *cur = (*cur).max(status);
+            }
+        }
+        debug!("loaded {} git statuses", statuses.len());
+        Ok(DirStatus { dir, statuses })
+    }
+
+    /// Load directory statuses if the directory is in a repository.
+    pub fn for_dir(dir: &Path, options: &ViewOptions) -> Option<DirStatus> {
+        if options.speed == ViewSpeed::Fast {
+            return None;
+        }
+        let repo = open_repo(dir)?;
+        match DirStatus::load(&repo, dir) {
+            Ok(s) => Some(s),
+            Err(e) => {
+                warn!("cannot read git status: {}", e.message());
+                None
+            }
+        }
+    }
+
+    /// Get the status of a path relative to the directory.
+    pub fn get(&self, path: &Path) -> Option<GitStatus> {
+        let path = path.strip_prefix(&self.dir).unwrap_or(path);
+        self.statuses.get(path).copied()
+    }
+}
+
+/// Find the last commit that changed a file.
+pub fn last_commit(
+    repo: &Repository,
+    path: &Path,
+    limit: Option<usize>,
+) -> Result<Option<CommitInfo>, git2::Error> {
+    let rel = match repo_path(repo, path) {
+        // the repository root has no tree entry of its own
+        Some((_, rel)) if rel.as_os_str().is_empty() => return Ok(None),
+        Some((_, rel)) => rel,
+        None => return Ok(None),
+    };
+    let mut walk = repo.revwalk()?;
+    walk.set_sorting(Sort::TIME)?;
+    if walk.push_head().is_err() {
+        // no commits yet
+        return Ok(None);
+    }
+
+    for (i, oid) in walk.enumerate() {
+        if limit.is_some_and(|n| i >= n) {
+            debug!("gave up looking for last commit after {} commits", i);
+            break;
+        }
+        let commit = repo.find_commit(oid?)?;
+        let entry = tree_entry_id(&commit.tree()?, &rel);
+        if entry.is_none() {
+            continue;
+        }
+        let changed = if commit.parent_count() == 0 {
+            true
+        } else {
+            commit
+                .parents()
+                .all(|p| p.tree().ok().and_then(|t| tree_entry_id(&t, &rel)) != entry)
+        };
+        if changed {
+            let author = commit.author();
+            let time = DateTime::from_timestamp(commit.time().seconds(), 0)
+                .map(|t| t.with_timezone(&Local))
+                .unwrap_or_default();
+            return Ok(Some(CommitInfo {
+                id: commit
+                    .as_object()
+                    .short_id()?
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                summary: commit.summary().unwrap_or_default().to_string(),
+                author: author.name().unwrap_or("unknown").to_string(),
+                time,
+            }));
+        }
+    }
+    Ok(None)
+}
+
+fn tree_entry_id(tree: &git2::Tree, path: &Path) -> Option<Oid> {
+    tree.get_path(path).ok().map(|e| e.id())
+}
+
+/// Get the git status and last commit fields for a file in a Meta view.
+pub fn git_fields(req: &FileRequest, options: &ViewOptions) -> Vec<Field> {
+    let mut fields = Vec::new();
+    // git only tracks the status and history of files
+    if options.speed == ViewSpeed::Fast || req.meta.as_ref().is_some_and(|m| m.is_dir()) {
+        return fields;
+    }
+    let repo = match open_repo(&req.path) {
+        Some(r) => r,
+        None => return fields,
+    };
+    let rel = match repo_path(&repo, &req.path) {
+        Some((_, rel)) if !rel.as_os_str().is_empty() => rel,
+        _ => return fields,
+    };
+
+    let status = match repo.status_file(&rel) {
+        Ok(flags) => GitStatus::from_status(flags),
+        Err(e) => {
+            debug!("{}: cannot read git status: {}", rel.display(), e.message());
+            return fields;
+        }
+    };
+    fields.push(Field::new(
+        "git_status",
+        "Git status",
+        status.map(|s| s.description()).unwrap_or("unmodified"),
+    ));
+    if matches!(
+        status,
+        Some(GitStatus::Untracked) | Some(GitStatus::Ignored)
+    ) {
+        return fields;
+    }
+
+    let limit = if options.speed == ViewSpeed::Slow {
+        None
+    } else {
+        Some(MAX_HISTORY)
+    };
+    match last_commit(&repo, &req.path, limit) {
+        Ok(Some(commit)) => {
+            fields.push(Field::new(
+                "last_commit",
+                "Last commit",
+                format!("{} {}", commit.id, commit.summary),
+            ));
+            fields.push(Field::new("author", "Author", commit.author));
+            fields.push(Field::new(
+                "date",
+                "Date",
+                commit.time.format("%Y-%m-%d %H:%M").to_string(),
+            ));
+        }
+        Ok(None) => (),
+        Err(e) => warn!("cannot read git history: {}", e.message()),
+    }
+    fields
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::{create_dir, write};
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    fn request(path: PathBuf) -> FileRequest {
+        FileRequest {
+            meta: path.metadata().ok(),
+            path,
+            link_target: None,
+            mime_type: "text/plain".into(),
+        }
+    }
+
+    fn labels(fields: &[Field]) -> Vec<&str> {
+        fields.iter().map(|f| f.label.as_str()).collect()
+    }
+
+    #[test]
+    fn describes_files_only() {
+        let dir = TempDir::new().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        create_dir(dir.path().join("src")).unwrap();
+        write(dir.path().join("src").join("a.txt"), "a\n").unwrap();
+        let mut index = repo.index().unwrap();
+        index.add_path(Path::new("src/a.txt")).unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let sig = git2::Signature::now("Test", "test@example.com").unwrap();
+        repo.commit(Some("HEAD"), &sig, &sig, "add a", &tree, &[])
+            .unwrap();
+        write(dir.path().join("new.txt"), "new\n").unwrap();
+        let options = ViewOptions::default();
+
+        let file = git_fields(&request(dir.path().join("src").join("a.txt")), &options);
+        assert_eq!(
+            labels(&file),
+            ["Git status", "Last commit", "Author", "Date"]
+        );
+        let new = git_fields(&request(dir.path().join("new.txt")), &options);
+        assert_eq!(labels(&new), ["Git status"]);
+        assert!(git_fields(&request(dir.path().join("src")), &options).is_empty());
+        assert!(git_fields(&request(dir.path().to_path_buf()), &options).is_empty());
+        assert!(last_commit(&repo, dir.path(), None).unwrap().is_none());
+    }
+}
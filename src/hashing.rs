@@ -0,0 +1,132 @@
+//! File checksums.
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+use md5::Md5;
+use sha2::{Digest, Sha256};
+
+use crate::interface::HashAlgorithm;
+
+const BUFSIZE: usize = 64 * 1024;
+
+/// A checksum being computed.
+enum Hasher {
+    Sha256(Sha256),
+    Blake3(Box<blake3::Hasher>),
+    Md5(Md5),
+    Crc32(crc32fast::Hasher),
+}
+
+impl HashAlgorithm {
+    /// Get the key for the algorithm's field in machine-readable output.
+    pub fn key(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "sha256",
+            HashAlgorithm::Blake3 => "blake3",
+            HashAlgorithm::Md5 => "md5",
+            HashAlgorithm::Crc32 => "crc32",
+        }
+    }
+
+    /// Get the display name of the algorithm.
+    pub fn name(&self) -> &'static str {
+        match self {
+            HashAlgorithm::Sha256 => "SHA-256",
+            HashAlgorithm::Blake3 => "BLAKE3",
+            HashAlgorithm::Md5 => "MD5",
+            HashAlgorithm::Crc32 => "CRC32",
+        }
+    }
+}
+
+impl Hasher {
+    fn new(algo: HashAlgorithm) -> Hasher {
+        match algo {
+            HashAlgorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            HashAlgorithm::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgorithm::Md5 => Hasher::Md5(Md5::new()),
+            HashAlgorithm::Crc32 => Hasher::Crc32(crc32fast::Hasher::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+            Hasher::Md5(h) => h.update(data),
+            Hasher::Crc32(h) => h.update(data),
+        }
+    }
+
+    /// Finish the checksum and format it as hexadecimal.
+    fn finish(self) -> String {
+        match self {
+            Hasher::Sha256(h) => hex(&h.finalize()),
+            Hasher::Blake3(h) => h.finalize().to_hex().to_string(),
+            Hasher::Md5(h) => hex(&h.finalize()),
+            Hasher::Crc32(h) => format!("{:08x}", h.finalize()),
+        }
+    }
+}
+
+/// Compute checksums of a file with several algorithms in a single pass.
+/// Repeated algorithms are only computed once.
+pub fn hash_file(path: &Path, algos: &[HashAlgorithm]) -> io::Result<Vec<(HashAlgorithm, String)>> {
+    let mut hashers: Vec<(HashAlgorithm, Hasher)> = Vec::with_capacity(algos.len());
+    for algo in algos {
+        // algorithms may be repeated on the command line and in the config
+        if !hashers.iter().any(|(a, _)| a == algo) {
+            hashers.push((*algo, Hasher::new(*algo)));
+        }
+    }
+    let mut file = File::open(path)?;
+    let mut buf = vec![0; BUFSIZE];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        for (_, h) in hashers.iter_mut() {
+            h.update(&buf[..n]);
+        }
+    }
+    Ok(hashers.into_iter().map(|(a, h)| (a, h.finish())).collect())
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use tempfile::NamedTempFile;
+
+    use super::*;
+
+    #[test]
+    fn hashes_each_algorithm_once() {
+        let mut file = NamedTempFile::new().unwrap();
+        file.write_all(b"abc").unwrap();
+        let algos = [
+            HashAlgorithm::Sha256,
+            HashAlgorithm::Crc32,
+            HashAlgorithm::Sha256,
+        ];
+        let hashes = hash_file(file.path(), &algos).unwrap();
+        assert_eq!(
+            hashes,
+            [
+                (
+                    HashAlgorithm::Sha256,
+                    "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad".into()
+                ),
+                (HashAlgorithm::Crc32, "352441c2".into()),
+            ]
+        );
+    }
+}
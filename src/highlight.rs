@@ -0,0 +1,173 @@
+//! Syntax highlighting for source text.
+use std::cell::OnceCell;
+use std::convert::Infallible;
+use std::fmt::Write;
+use std::path::Path;
+use std::rc::Rc;
+use std::thread_local;
+
+use ansi_colours::ansi256_from_rgb;
+use bat::assets::HighlightingAssets;
+use bat::SyntaxMapping;
+use log::*;
+use syntect::easy::HighlightLines;
+use syntect::highlighting::{Color, Style};
+use syntect::parsing::{SyntaxReference, SyntaxSet};
+
+use crate::styling::{color_enabled, true_color};
+
+thread_local! {
+    static ASSETS: OnceCell<Rc<HighlightingAssets>> = const { OnceCell::new() };
+}
+
+/// How to find the syntax for highlighting.
+pub enum SyntaxHint<'a> {
+    /// A language name or extension, as in Markdown code blocks.
+    Token(&'a str),
+    /// The path of a source file.
+    Path(&'a Path),
+}
+
+fn assets() -> Rc<HighlightingAssets> {
+    ASSETS.with(|c| {
+        c.get_or_init(|| {
+            debug!("loading highlighting assets");
+            Rc::new(HighlightingAssets::from_binary())
+        })
+        .clone()
+    })
+}
+
+/// Highlight source text with terminal escapes, returning its lines.  The
+/// text is returned unchanged if color is disabled or highlighting fails.
+pub fn highlight(code: &str, hint: SyntaxHint, theme: Option<&str>) -> Vec<String> {
+    let mut out = Vec::new();
+    let lines = code.lines().map(|l| Ok::<_, Infallible>(l.to_string()));
+    let Ok(()) = highlight_lines(lines, hint, theme, |line| {
+        out.push(line.to_string());
+        Ok(())
+    });
+    out
+}
+
+/// Highlight lines of source text as they are read, passing each one to
+/// `emit`.  Lines are passed unchanged if color is disabled or highlighting
+/// fails.
+pub fn highlight_lines<I, E, F>(
+    lines: I,
+    hint: SyntaxHint,
+    theme: Option<&str>,
+    mut emit: F,
+) -> Result<(), E>
+where
+    I: IntoIterator<Item = Result<String, E>>,
+    F: FnMut(&str) -> Result<(), E>,
+{
+    let mut lines = lines.into_iter().peekable();
+    let assets = assets();
+    let syntaxes = if color_enabled() {
+        assets
+            .get_syntax_set()
+            .map_err(|e| warn!("cannot load syntax definitions: {}", e))
+            .ok()
+    } else {
+        None
+    };
+    let mut highlighter = syntaxes.map(|ss| {
+        let first = match lines.peek() {
+            Some(Ok(line)) => Some(line.as_str()),
+            _ => None,
+        };
+        let syntax = find_syntax(&assets, ss, &hint, first);
+        let theme = assets.get_theme(theme.unwrap_or(HighlightingAssets::default_theme()));
+        (HighlightLines::new(syntax, theme), ss, syntax)
+    });
+    let true_color = true_color();
+
+    for line in lines {
+        let line = line? + "\n";
+        let ranges = highlighter
+            .as_mut()
+            .map(|(hl, ss, _)| hl.highlight_line(&line, ss));
+        match ranges {
+            Some(Ok(ranges)) => emit(&escaped(&ranges, true_color))?,
+            Some(Err(e)) => {
+                if let Some((_, _, syntax)) = highlighter.take() {
+                    warn!("error highlighting {}: {}", syntax.name, e);
+                }
+                emit(line.trim_end_matches('\n'))?;
+            }
+            None => emit(line.trim_end_matches('\n'))?,
+        }
+    }
+    Ok(())
+}
+
+/// Convert highlighted text to terminal escapes.  Colors are approximated
+/// in the 256-color palette unless the terminal supports 24-bit color.
+fn escaped(ranges: &[(Style, &str)], true_color: bool) -> String {
+    let mut out = String::new();
+    for (style, text) in ranges {
+        let Color { r, g, b, a } = style.foreground;
+        // bat's ANSI themes store palette indices with zero alpha
+        match (a, true_color) {
+            (0, _) => write!(out, "\x1b[38;5;{}m", r),
+            (1, _) => write!(out, "\x1b[39m"),
+            (_, true) => write!(out, "\x1b[38;2;{};{};{}m", r, g, b),
+            (_, false) => write!(out, "\x1b[38;5;{}m", ansi256_from_rgb((r, g, b))),
+        }
+        .expect("string write failed");
+        out.push_str(text.trim_end_matches(['\r', '\n']));
+    }
+    out.push_str("\x1b[0m");
+    out
+}
+
+fn find_syntax<'a>(
+    assets: &'a HighlightingAssets,
+    syntaxes: &'a SyntaxSet,
+    hint: &SyntaxHint,
+    first: Option<&str>,
+) -> &'a SyntaxReference {
+    let found = match hint {
+        SyntaxHint::Token(lang) => syntaxes.find_syntax_by_token(lang),
+        SyntaxHint::Path(path) => assets
+            .get_syntax_for_path(path, &SyntaxMapping::builtin())
+            .ok()
+            .map(|s| s.syntax),
+    };
+    found
+        .or_else(|| syntaxes.find_syntax_by_first_line(first?))
+        .unwrap_or_else(|| syntaxes.find_syntax_plain_text())
+}
+
+#[cfg(test)]
+mod tests {
+    use syntect::highlighting::FontStyle;
+
+    use super::*;
+
+    fn ranges() -> Vec<(Style, &'static str)> {
+        let style = Style {
+            foreground: Color {
+                r: 255,
+                g: 0,
+                b: 0,
+                a: 255,
+            },
+            background: Color::BLACK,
+            font_style: FontStyle::empty(),
+        };
+        vec![(style, "fn\n")]
+    }
+
+    #[test]
+    fn escapes_true_color() {
+        assert_eq!(escaped(&ranges(), true), "\x1b[38;2;255;0;0mfn\x1b[0m");
+    }
+
+    #[test]
+    fn escapes_256_color() {
+        assert_eq!(escaped(&ranges(), false), "\x1b[38;5;196mfn\x1b[0m");
+    }
+}
@@ -4,10 +4,16 @@ use std::fs::Metadata;
 use std::io;
 use std::os::unix::fs::MetadataExt;
 use std::path::PathBuf;
+use std::process::Command;
+use std::time::Duration;
 
+use anstyle::Style;
 use colorchoice::ColorChoice;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
+use crate::config::ViewerConfig;
+use crate::fileinfo::file_meta_fields;
 use crate::programs::ProgramError;
 
 /// Request for speed of operations.
@@ -15,9 +21,11 @@ use crate::programs::ProgramError;
 /// This is primarily respected by the meta operation — viewing often needs to
 /// display data. The exact interpretation of these is up to individual
 /// backends.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ViewSpeed {
     /// Default speed limit — should return in a second or two.
+    #[default]
     Default,
     /// Avoid all slow operations.
     Fast,
@@ -26,7 +34,8 @@ pub enum ViewSpeed {
 }
 
 /// Enum for the different view types.
-#[derive(Debug, Clone, Eq, PartialEq)]
+#[derive(Debug, Clone, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ViewType {
     /// View file metadata.
     Meta,
@@ -36,6 +45,44 @@ pub enum ViewType {
     Full,
 }
 
+/// Sort order for directory listings.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortKey {
+    /// Sort by file name.
+    #[default]
+    Name,
+    /// Sort by size, largest first.
+    Size,
+    /// Sort by modification time, newest first.
+    Time,
+    /// Sort by detected MIME type.
+    Type,
+}
+
+/// Output formats.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Human-readable text for the terminal, with colors and paging.
+    #[default]
+    Text,
+    /// Plain text without colors or paging.
+    Plain,
+    /// JSON document.
+    Json,
+}
+
+/// Checksum algorithms for the Meta view.
+#[derive(Debug, Clone, Copy, Eq, PartialEq, clap::ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HashAlgorithm {
+    Sha256,
+    Blake3,
+    Md5,
+    Crc32,
+}
+
 /// Errors from viewing backends.
 #[derive(Debug, Error)]
 pub enum ViewError {
@@ -47,6 +94,9 @@ pub enum ViewError {
     External(#[from] ProgramError),
     #[error("view error: {0}")]
     Unspecified(String),
+    /// An error after output was written, so another view cannot replace it.
+    #[error(transparent)]
+    AfterOutput(Box<ViewError>),
 }
 
 impl From<String> for ViewError {
@@ -72,20 +122,53 @@ impl ViewError {
 pub struct FileRequest {
     /// Path to the file to display.
     pub path: PathBuf,
-    /// File metadata (from [std::fs::metadata]).
+    /// File metadata (from [std::fs::metadata]).  This follows symbolic
+    /// links, and is `None` if the path is a dangling link.
     pub meta: Option<Metadata>,
+    /// The target of the path, if it is a symbolic link.
+    pub link_target: Option<PathBuf>,
     /// The file's MIME type.
     pub mime_type: String,
 }
 
 /// Options for a view.
-#[allow(dead_code)]
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct ViewOptions {
     /// Whether to use a long display with more details.
     pub long_display: bool,
     /// The requested view speed.
     pub speed: ViewSpeed,
+    /// Whether to show raw source instead of rendering documents.
+    pub raw_source: bool,
+    /// Sort order for directory listings.
+    pub sort: SortKey,
+    /// Whether to list directories with the native lister instead of an
+    /// external program.
+    pub native_ls: bool,
+    /// Whether to show hidden files (dotfiles) in directory listings.
+    pub show_hidden: bool,
+    /// Whether to hide files ignored by git in directory listings.
+    pub hide_ignored: bool,
+    /// Whether to show directories as a tree.
+    pub tree: bool,
+    /// Maximum depth for directory trees.
+    pub max_depth: Option<usize>,
+    /// Whether external directory listers should show file icons.
+    pub icons: bool,
+    /// Checksums to compute in Meta views.
+    pub hashes: Vec<HashAlgorithm>,
+    /// The output format.
+    pub format: OutputFormat,
+    /// Name of a backend to use instead of automatic selection.
+    pub backend: Option<String>,
+    /// Pager command, used instead of `$PAGER`.
+    pub pager: Option<String>,
+    /// Preferred external directory listers, tried before the others.
+    pub listers: Vec<String>,
+    /// Syntax highlighting theme.
+    pub theme: Option<String>,
+    /// User-defined external viewers.
+    pub viewers: Vec<ViewerConfig>,
     /// The user's sepcified choice of color mode.
     ///
     /// Styling functions will automatically respect the color choice, this
@@ -102,22 +185,287 @@ pub struct ViewOptions {
 /// Interface for file view backends.
 ///
 /// A viewer is selected by processing the viewers in definition order and
-/// checking each one with [FileViewer::can_view].
+/// checking each one with [FileViewer::can_view].  If the view fails, later
+/// viewers are tried.
 pub trait FileViewer {
     /// Obtain a view if this backend can supply one.
     ///
     /// If `mode` is `None`, the backend should return a default view for this
     /// file, if it can supply one.
     fn make_view(&self, req: &FileRequest, mode: &Option<ViewType>) -> Option<Box<dyn FileView>>;
+
+    /// Get the backend's name, for selecting it with `--backend`.
+    fn name(&self) -> &str;
 }
 
 /// Implementation of a single file view request.
 pub trait FileView {
-    fn display(&self, req: &FileRequest, options: &ViewOptions) -> Result<(), ViewError>;
+    /// Produce the view's output for rendering.
+    fn render(&self, req: &FileRequest, options: &ViewOptions) -> Result<ViewOutput, ViewError>;
+}
+
+/// Metadata views that describe a file with structured fields.
+///
+/// These are displayed with the common file metadata added.
+pub trait MetaView {
+    fn describe(&self, req: &FileRequest, options: &ViewOptions) -> Result<MetaInfo, ViewError>;
+}
+
+impl<V: MetaView> FileView for V {
+    fn render(&self, req: &FileRequest, options: &ViewOptions) -> Result<ViewOutput, ViewError> {
+        let mut info = self.describe(req, options)?;
+        info.fields.extend(file_meta_fields(req, options));
+        Ok(ViewOutput::new(vec![Block::Meta(info)]))
+    }
+}
+
+/// Output of a view, separated from how it is rendered.
+pub struct ViewOutput {
+    pub blocks: Vec<Block>,
+    /// Whether to page the output when rendering to a terminal.
+    pub paged: bool,
+}
+
+/// A block of view output.
+pub enum Block {
+    /// A file description with metadata fields.
+    Meta(MetaInfo),
+    /// A titled group of blocks.
+    Section(String, Vec<Block>),
+    /// Labeled fields.
+    Fields(Vec<Field>),
+    Table(Table),
+    /// Lines of text, possibly styled for the terminal.
+    Text(Vec<String>),
+    /// Source text from a file, syntax-highlighted on the terminal.
+    Source {
+        path: PathBuf,
+        /// Maximum number of lines to show.
+        lines: Option<usize>,
+    },
+    /// Content passed through from outside the view.
+    Raw(Passthrough),
+}
+
+/// Sources of passthrough output.
+pub enum Passthrough {
+    /// Output of an external program, with the nonzero exit codes it uses
+    /// to report minor problems alongside its output (such as `ls` failing to
+    /// read an entry).
+    Command(Command, &'static [i32]),
+    /// Contents of a file (or FIFO).
+    File(PathBuf),
+    /// In-process code writing directly to standard output.  This cannot be
+    /// captured for structured output.
+    Internal(Box<dyn FnOnce() -> Result<(), ViewError>>),
+}
+
+/// A table of values.
+#[derive(Debug, Clone)]
+pub struct Table {
+    pub columns: Vec<Column>,
+    pub rows: Vec<Vec<Value>>,
+    /// Whether to show the column names on the terminal.
+    pub headings: bool,
+}
+
+/// A table column.
+#[derive(Debug, Clone)]
+pub struct Column {
+    pub name: String,
+    pub align: Align,
+}
+
+/// Alignment of table columns.
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
+}
+
+impl ViewOutput {
+    pub fn new(blocks: Vec<Block>) -> ViewOutput {
+        ViewOutput {
+            blocks,
+            paged: false,
+        }
+    }
+
+    /// Create output that is paged on the terminal.
+    pub fn paged(blocks: Vec<Block>) -> ViewOutput {
+        ViewOutput {
+            blocks,
+            paged: true,
+        }
+    }
+}
+
+impl Column {
+    pub fn new<S: Into<String>>(name: S, align: Align) -> Column {
+        Column {
+            name: name.into(),
+            align,
+        }
+    }
+}
+
+/// Structured description of a file from a metadata view.
+#[derive(Debug, Clone)]
+pub struct MetaInfo {
+    /// Description of the file type.
+    pub description: String,
+    /// Summary of the content for the headline (e.g. the number of lines).
+    pub summary: Option<Field>,
+    /// The file size.
+    pub size: Option<u64>,
+    /// Detail fields.
+    pub fields: Vec<Field>,
+}
+
+/// A labeled metadata field.
+#[derive(Debug, Clone)]
+pub struct Field {
+    /// Stable identifier for machine-readable output.
+    pub key: String,
+    /// Label for display, which may be reworded.
+    pub label: String,
+    pub value: Value,
+}
+
+/// Values of metadata fields.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Text(String),
+    Integer(i64),
+    /// A count of things, with the (plural) unit.
+    Count(u64, &'static str),
+    /// A measurement with a unit.
+    Quantity(f64, &'static str),
+    /// A size in bytes.
+    Bytes(u64),
+    Duration(Duration),
+    /// Width and height in pixels.
+    Dimensions(u32, u32),
+    /// Text with a display style.
+    Styled(String, &'static Style),
+    List(Vec<Value>),
+    Record(Vec<Field>),
+}
+
+impl MetaInfo {
+    pub fn new<S: Into<String>>(description: S) -> MetaInfo {
+        MetaInfo {
+            description: description.into(),
+            summary: None,
+            size: None,
+            fields: Vec::new(),
+        }
+    }
+
+    /// Add a field.
+    pub fn add<S: Into<String>, V: Into<Value>>(&mut self, key: &str, label: S, value: V) {
+        self.fields.push(Field::new(key, label, value));
+    }
+
+    /// Add a field if it has a value.
+    pub fn add_opt<S: Into<String>, V: Into<Value>>(
+        &mut self,
+        key: &str,
+        label: S,
+        value: Option<V>,
+    ) {
+        if let Some(v) = value {
+            self.add(key, label, v);
+        }
+    }
+}
+
+impl Field {
+    pub fn new<S: Into<String>, V: Into<Value>>(key: &str, label: S, value: V) -> Field {
+        Field {
+            key: key.into(),
+            label: label.into(),
+            value: value.into(),
+        }
+    }
+
+    /// Create a field whose label comes from the file, such as a tag or
+    /// attribute name, deriving its key from the label.
+    pub fn from_label<S: Into<String>, V: Into<Value>>(label: S, value: V) -> Field {
+        let label = label.into();
+        Field {
+            key: label_key(&label),
+            label,
+            value: value.into(),
+        }
+    }
+}
+
+/// Convert a label to a key: lower case, with words joined by underscores.
+fn label_key(label: &str) -> String {
+    label
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect::<Vec<_>>()
+        .join("_")
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Text(value)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Text(value.to_string())
+    }
+}
+
+impl From<&String> for Value {
+    fn from(value: &String) -> Self {
+        Value::Text(value.clone())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(value: i64) -> Self {
+        Value::Integer(value)
+    }
+}
+
+impl From<u64> for Value {
+    fn from(value: u64) -> Self {
+        Value::Integer(value as i64)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Integer(value.into())
+    }
+}
+
+impl From<u16> for Value {
+    fn from(value: u16) -> Self {
+        Value::Integer(value.into())
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Integer(value as i64)
+    }
 }
 
 impl FileRequest {
     pub fn file_size(&self) -> Option<u64> {
         self.meta.as_ref().map(|m| m.size())
     }
+
+    /// Query whether the path is a symbolic link whose target does not exist.
+    pub fn is_dangling(&self) -> bool {
+        self.link_target.is_some() && self.meta.is_none()
+    }
 }
@@ -0,0 +1,218 @@
+//! Automatically view files and file information.
+//!
+//! This library provides AutoView's file type detection and backend dispatch
+//! for use in other programs.  The simplest entry point is [view_path], which
+//! detects a file's type and returns the output of the first backend that can
+//! view it; the output can be displayed with [render::render_output] or
+//! inspected directly.
+use std::fs::{canonicalize, symlink_metadata, Metadata};
+use std::path::{Path, PathBuf};
+
+use log::*;
+use shared_mime::{Answer, FileQuery, MimeDB};
+
+use crate::programs::ProgramError;
+
+pub mod backends;
+pub mod config;
+mod explain;
+mod fileinfo;
+mod git;
+mod hashing;
+mod highlight;
+pub mod interface;
+mod links;
+pub mod mime;
+pub mod pager;
+pub mod programs;
+pub mod render;
+mod styling;
+
+pub use explain::explain_request;
+pub use interface::{
+    FileRequest, FileView, FileViewer, OutputFormat, ViewError, ViewOptions, ViewOutput, ViewSpeed,
+    ViewType,
+};
+pub use styling::set_color_enabled;
+
+/// Guess the MIME type of a file.
+pub fn guess_type<'a>(db: &'a MimeDB, path: &Path) -> Result<Answer<'a>, ViewError> {
+    let (meta, link_target) = links::resolve_path(path)?;
+    query_type(db, path, &meta, &link_target)
+}
+
+/// Detect a file's type and create a request to view it.
+pub fn file_request(path: &Path) -> Result<FileRequest, ViewError> {
+    let (meta, link_target) = links::resolve_path(path)?;
+    let db = mime::mime_db();
+    let guess = query_type(&db, path, &meta, &link_target)?;
+    Ok(FileRequest {
+        path: path.to_path_buf(),
+        meta,
+        link_target,
+        mime_type: guess.best().unwrap_or("application/octet-stream").into(),
+    })
+}
+
+/// View a file with the first backend that supports it.
+///
+/// If `view` is `None`, the backend's default view is used.
+pub fn view_path(
+    path: &Path,
+    view: Option<ViewType>,
+    options: &ViewOptions,
+) -> Result<ViewOutput, ViewError> {
+    let req = file_request(path)?;
+    view_request(&req, view, options)
+}
+
+/// View a file request with the first backend that supports it.
+///
+/// If a backend's view fails, the remaining backends are tried; if none
+/// succeed, the first error is returned.  External programs in the output are
+/// not run until it is rendered, so their failures are not covered; use
+/// [show_request] to fall back when they fail.
+pub fn view_request(
+    req: &FileRequest,
+    view: Option<ViewType>,
+    options: &ViewOptions,
+) -> Result<ViewOutput, ViewError> {
+    try_backends(req, view, options, |v| v.render(req, options))
+}
+
+/// View a file request and render its output, falling back to later backends
+/// if the view or rendering it (including running external programs) fails
+/// before any output is written.
+pub fn show_request(
+    req: &FileRequest,
+    view: Option<ViewType>,
+    options: &ViewOptions,
+) -> Result<(), ViewError> {
+    try_backends(req, view, options, |v| {
+        let output = v.render(req, options)?;
+        render::render_output(req, output, options)
+    })
+}
+
+/// Apply a function to the view from each backend that supports the request,
+/// until one succeeds.
+fn try_backends<T, F>(
+    req: &FileRequest,
+    view: Option<ViewType>,
+    options: &ViewOptions,
+    mut func: F,
+) -> Result<T, ViewError>
+where
+    F: FnMut(Box<dyn FileView>) -> Result<T, ViewError>,
+{
+    set_color_enabled(options.color_enabled);
+    let mut error = None;
+    for back in backends::selected_backends(options)? {
+        let v = match back.make_view(req, &view) {
+            Some(v) => v,
+            None => continue,
+        };
+        info!("trying backend {}", back.name());
+        match func(v) {
+            Ok(res) => return Ok(res),
+            // output already written cannot be replaced by another view
+            Err(ViewError::AfterOutput(e)) => return Err(*e),
+            // and another view would use the same invalid pager
+            Err(e @ ViewError::External(ProgramError::InvalidCommand(..))) => return Err(e),
+            Err(e) => {
+                info!("backend {} failed: {}", back.name(), e);
+                error.get_or_insert(e);
+            }
+        }
+    }
+    Err(error.unwrap_or_else(|| match &options.backend {
+        Some(name) => format!("backend {} cannot view {}", name, req.mime_type).into(),
+        None => format!("no backend can view {}", req.mime_type).into(),
+    }))
+}
+
+fn query_type<'a>(
+    db: &'a MimeDB,
+    path: &Path,
+    meta: &Option<Metadata>,
+    link_target: &Option<PathBuf>,
+) -> Result<Answer<'a>, ViewError> {
+    debug!("guessing type from {}", path.display());
+    // detect links from their targets, and dangling links from the link itself
+    let resolved = match (meta, link_target) {
+        (Some(_), Some(_)) => canonicalize(path)?,
+        _ => path.to_path_buf(),
+    };
+    let query = if meta.is_some() {
+        FileQuery::for_path(&resolved).map_err(ViewError::wrap)?
+    } else {
+        FileQuery::builder()
+            .metadata(symlink_metadata(path)?)
+            .build()
+    };
+    let guess = db.query(&query).map_err(ViewError::wrap)?;
+    debug!("mime type result: {:?}", guess);
+    Ok(guess)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs::write;
+
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::config::ViewerConfig;
+    use crate::interface::{Block, Passthrough};
+
+    /// Options with a viewer whose program fails for text files.
+    fn failing_viewer() -> ViewOptions {
+        ViewOptions {
+            format: OutputFormat::Json,
+            viewers: vec![ViewerConfig {
+                name: "fail".into(),
+                mime: None,
+                glob: Some("*.txt".into()),
+                meta: None,
+                head: None,
+                full: Some("false".into()),
+                paged: false,
+            }],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn falls_back_when_program_fails() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("test.txt");
+        write(&path, "hello\n").unwrap();
+        let req = file_request(&path).unwrap();
+        let mut options = failing_viewer();
+
+        // the program is not run until rendering
+        let output = view_request(&req, None, &options).unwrap();
+        assert!(matches!(
+            output.blocks.as_slice(),
+            [Block::Raw(Passthrough::Command(..))]
+        ));
+        // so showing the file falls back to the next backend
+        show_request(&req, None, &options).unwrap();
+
+        options.backend = Some("fail".into());
+        assert!(show_request(&req, None, &options).is_err());
+    }
+
+    #[test]
+    fn keeps_output_already_written() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join("test.txt");
+        write(&path, "hello\n").unwrap();
+        let req = file_request(&path).unwrap();
+        let mut options = failing_viewer();
+        // the program writes straight to standard output, so its failure
+        // cannot be replaced with another view
+        options.format = OutputFormat::Plain;
+        assert!(show_request(&req, None, &options).is_err());
+    }
+}
@@ -0,0 +1,66 @@
+//! Symbolic link handling.
+use std::fs::{metadata, read_link, symlink_metadata, Metadata};
+use std::io;
+use std::path::{Path, PathBuf};
+
+use log::*;
+
+use crate::interface::{Field, FileRequest, Value};
+use crate::styling::{FILE_TYPE, MISSING, SYMLINK};
+
+/// Resolve a path into its file metadata and link target.
+///
+/// The metadata follows links, and is `None` if the path is a dangling link.
+pub fn resolve_path(path: &Path) -> io::Result<(Option<Metadata>, Option<PathBuf>)> {
+    let lmeta = symlink_metadata(path)?;
+    if !lmeta.is_symlink() {
+        return Ok((Some(lmeta), None));
+    }
+
+    let target = read_link(path)?;
+    debug!("{}: link to {}", path.display(), target.display());
+    let meta = match metadata(path) {
+        Ok(m) => Some(m),
+        Err(e) => {
+            info!("{}: dangling link: {}", path.display(), e);
+            None
+        }
+    };
+    Ok((meta, Some(target)))
+}
+
+/// Get the link target and resolution fields for a Meta view.
+pub fn link_fields(req: &FileRequest) -> Vec<Field> {
+    let target = match &req.link_target {
+        Some(t) => t,
+        None => return Vec::new(),
+    };
+    let mut fields = vec![Field::new(
+        "link_target",
+        "Link target",
+        Value::Styled(target.display().to_string(), &SYMLINK),
+    )];
+    if req.is_dangling() {
+        fields.push(Field::new(
+            "link_status",
+            "Link status",
+            Value::Styled("dangling".into(), &MISSING),
+        ));
+        return fields;
+    }
+    if let Ok(path) = req.path.canonicalize() {
+        if path != *target {
+            fields.push(Field::new(
+                "resolved_path",
+                "Resolved path",
+                path.display().to_string(),
+            ));
+        }
+    }
+    fields.push(Field::new(
+        "resolved_type",
+        "Resolved type",
+        Value::Styled(req.mime_type.clone(), &FILE_TYPE),
+    ));
+    fields
+}
@@ -1,21 +1,18 @@
 use std::io::{stdout, IsTerminal};
-use std::{fs::metadata, path::PathBuf};
+use std::path::PathBuf;
 
 use anyhow::Result;
+use autoview::config::Config;
+use autoview::interface::{HashAlgorithm, SortKey};
+use autoview::mime::mime_db;
+use autoview::render::render_output;
+use autoview::{explain_request, file_request, guess_type, show_request};
+use autoview::{OutputFormat, ViewOptions, ViewSpeed, ViewType};
 use clap::{Args, CommandFactory, FromArgMatches, Parser};
 use colorchoice::ColorChoice;
-use interface::{FileRequest, ViewOptions, ViewSpeed, ViewType};
 use log::*;
-use shared_mime::{Answer, FileQuery, MimeDB};
+use shared_mime::{Answer, MimeDB};
 use stderrlog::StdErrLog;
-use styling::set_color_enabled;
-
-mod backends;
-mod interface;
-pub mod mime;
-pub mod pager;
-pub mod programs;
-mod styling;
 
 #[cfg(not(feature = "gpl"))]
 static LICENSE_HEADER: &str =
@@ -26,6 +23,7 @@ static LICENSE_HEADER: &str = "Copyright (c) Michael Ekstrand. Free software und
 /// Automatically view files and file information.
 #[derive(Parser)]
 #[command(name = "autoview", version = "0.1.0")]
+#[allow(clippy::upper_case_acronyms)]
 struct CLI {
     #[command(flatten)]
     action: AVAction,
@@ -42,6 +40,59 @@ struct CLI {
     #[arg(short = 'l', long = "long")]
     long: bool,
 
+    /// Show raw source instead of rendering documents (e.g. Markdown)
+    #[arg(short = 'r', long = "raw")]
+    raw: bool,
+
+    /// Sort order for directory listings
+    #[arg(long = "sort", value_enum)]
+    sort: Option<SortKey>,
+
+    /// List directories with autoview's own lister, showing MIME types
+    /// (instead of eza, lsd, ls, etc.)
+    #[arg(long = "native")]
+    native_ls: bool,
+
+    /// Show hidden files (dotfiles) in directory listings
+    #[arg(short = 'a', long = "all")]
+    all: bool,
+
+    /// Hide files ignored by git in directory listings
+    #[arg(long = "git-ignore")]
+    git_ignore: bool,
+
+    /// Show directories as a tree
+    #[arg(long = "tree")]
+    tree: bool,
+
+    /// Limit the depth of directory trees [default: 3, unlimited with --slow]
+    #[arg(short = 'L', long = "depth", requires = "tree")]
+    depth: Option<usize>,
+
+    /// Show file icons when listing directories with an external program
+    #[arg(long = "icons")]
+    icons: bool,
+
+    /// Output format
+    #[arg(long = "format", value_enum)]
+    format: Option<OutputFormat>,
+
+    /// Checksums to show in the metadata view (comma-separated)
+    #[arg(long = "hash", value_enum, value_delimiter = ',')]
+    hash: Vec<HashAlgorithm>,
+
+    /// Use this MIME type instead of detecting the file's type
+    #[arg(long = "type", value_name = "MIME")]
+    mime_override: Option<String>,
+
+    /// Use the named backend instead of selecting one automatically
+    #[arg(long = "backend", value_name = "NAME")]
+    backend: Option<String>,
+
+    /// Explain the detected type and which backend would view the file
+    #[arg(long = "explain", conflicts_with = "mime_type")]
+    explain: bool,
+
     /// Avoid slow display or listing operations
     #[arg(short = 'f', long = "fast")]
     fast: bool,
@@ -83,8 +134,16 @@ fn main() -> Result<()> {
         .verbosity(cli.verbose as usize + 1)
         .init()?;
     info!("CLI launching");
+    let config = Config::load()?;
+    let defaults = &config.defaults;
+    let format = cli.format.or(defaults.format).unwrap_or_default();
 
     let color_choice = ColorChoice::global();
+    // only the terminal format is styled
+    let color_choice = match format {
+        OutputFormat::Text => color_choice,
+        _ => ColorChoice::Never,
+    };
     let color_enabled = match color_choice {
         ColorChoice::Always | ColorChoice::AlwaysAnsi => true,
         ColorChoice::Never => false,
@@ -94,18 +153,23 @@ fn main() -> Result<()> {
         "color {}",
         if color_enabled { "enabled" } else { "disabled" }
     );
-    set_color_enabled(color_enabled);
-
-    let db = mime::mime_db();
-    debug!("guessing type from {}", cli.file.display());
-    let query = FileQuery::for_path(&cli.file)?;
-    let guess = db.query(&query)?;
-    debug!("mime type result: {:?}", guess);
 
     if cli.action.mime_type {
         info!("outputting MIME type information");
-        return cli.show_mime(db.as_ref(), &guess);
+        let db = mime_db();
+        let guess = guess_type(&db, &cli.file)?;
+        return cli.show_mime(&db, &guess);
+    }
+
+    let mut request = file_request(&cli.file)?;
+    if let Some(mime) = &cli.mime_override {
+        if mime_db().description(mime).is_none() {
+            warn!("{} is not a known MIME type", mime);
+        }
+        info!("using MIME type {} instead of {}", mime, request.mime_type);
+        request.mime_type = mime.clone();
     }
+    let prefs = config.type_prefs(&request.mime_type);
 
     let view = if cli.action.head {
         Some(ViewType::Head)
@@ -114,33 +178,50 @@ fn main() -> Result<()> {
     } else if cli.action.show {
         Some(ViewType::Full)
     } else {
-        None
+        prefs.and_then(|p| p.view.clone())
     };
 
-    let meta = metadata(&cli.file)?;
-    let request = FileRequest {
-        path: cli.file.clone(),
-        meta: Some(meta),
-        mime_type: guess.best().unwrap_or("application/octet-stream").into(),
-    };
     let options = ViewOptions {
-        long_display: cli.long,
+        long_display: cli.long || defaults.long,
         speed: if cli.fast {
             ViewSpeed::Fast
         } else if cli.slow {
             ViewSpeed::Slow
         } else {
-            ViewSpeed::Default
+            defaults.speed.clone().unwrap_or_default()
+        },
+        raw_source: cli.raw || defaults.raw,
+        sort: cli.sort.or(defaults.sort).unwrap_or_default(),
+        native_ls: cli.native_ls || defaults.native_ls,
+        show_hidden: cli.all || defaults.all,
+        hide_ignored: cli.git_ignore || defaults.git_ignore,
+        tree: cli.tree || defaults.tree,
+        max_depth: cli.depth,
+        icons: cli.icons || defaults.icons,
+        hashes: if cli.hash.is_empty() {
+            defaults.hash.clone()
+        } else {
+            cli.hash
         },
+        format,
+        backend: cli
+            .backend
+            .clone()
+            .or_else(|| prefs.and_then(|p| p.backend.clone())),
+        pager: config.pager.clone(),
+        listers: config.listers.clone(),
+        theme: config.theme.clone(),
+        viewers: config.viewers.clone(),
         color_choice,
         color_enabled,
     };
 
-    for back in backends::backends() {
-        if let Some(view) = back.make_view(&request, &view) {
-            view.display(&request, &options)?;
-            break;
-        }
+    if cli.explain {
+        info!("explaining backend selection");
+        let output = explain_request(&request, &view, &options)?;
+        render_output(&request, output, &options)?;
+    } else {
+        show_request(&request, view, &options)?;
     }
 
     Ok(())
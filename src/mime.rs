@@ -11,7 +11,7 @@ use shared_mime::MimeDB;
 use shared_mime_embedded::load_mime_db;
 
 thread_local! {
-    static MIME_DB: OnceCell<Rc<MimeDB>> = OnceCell::new();
+    static MIME_DB: OnceCell<Rc<MimeDB>> = const { OnceCell::new() };
 }
 
 /// Load or retrieve the MIME database.
@@ -30,3 +30,12 @@ pub fn mime_db() -> Rc<MimeDB> {
         dbref.clone()
     })
 }
+
+/// Check whether a MIME type matches a pattern.  Subtypes of the pattern
+/// match, and `type/*` matches every type in the family.
+pub fn matches_type(mime_type: &str, pattern: &str) -> bool {
+    match pattern.strip_suffix("/*") {
+        Some(family) => mime_type.split('/').next() == Some(family),
+        None => mime_db().is_subtype(mime_type, pattern),
+    }
+}
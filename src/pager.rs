@@ -1,23 +1,112 @@
 use std::env;
+use std::io::{self, ErrorKind, Write};
 use std::path::Path;
-use std::process::{Command, Stdio};
+use std::process::{Child, ChildStdin, Command, Stdio};
 
 use crate::programs::ProgramError;
 
-pub fn page_file(path: &Path) -> Result<(), ProgramError> {
-    let pager = env::var_os("PAGER").unwrap_or("less".into());
-    let status = Command::new(pager).arg(path.as_os_str()).status()?;
+/// Create the pager command, using the configured pager or `$PAGER`.  The
+/// command is split into words like a shell command.
+fn pager_command(pager: Option<&str>) -> Result<Command, ProgramError> {
+    let pager = match pager {
+        Some(p) => p.to_string(),
+        None => env::var("PAGER").unwrap_or("less".into()),
+    };
+    let words = shell_words::split(&pager).map_err(|e| ProgramError::InvalidCommand(pager, e))?;
+    let mut words = words.iter();
+    let mut cmd = Command::new(words.next().map(|w| w.as_str()).unwrap_or("less"));
+    cmd.args(words);
+    Ok(cmd)
+}
+
+pub fn page_file(path: &Path, pager: Option<&str>) -> Result<(), ProgramError> {
+    let status = pager_command(pager)?.arg(path.as_os_str()).status()?;
     ProgramError::check(status)
 }
 
-pub fn page_command(mut cmd: Command) -> Result<(), ProgramError> {
-    let pager = env::var_os("PAGER").unwrap_or("less".into());
+/// Page a program's output, accepting the `minor` exit codes it uses for
+/// minor problems.
+pub fn page_command(
+    mut cmd: Command,
+    minor: &[i32],
+    pager: Option<&str>,
+) -> Result<(), ProgramError> {
+    let mut pager = pager_command(pager)?;
     let mut child = cmd.stdout(Stdio::piped()).spawn()?;
     let data = child.stdout.take().expect("no stdout");
 
-    let pager_res = Command::new(pager).stdin(Stdio::from(data)).status()?;
+    let pager_res = pager.stdin(Stdio::from(data)).status()?;
     ProgramError::check(pager_res)?;
 
     let res = child.wait()?;
-    ProgramError::check(res)
+    ProgramError::check_minor(res, minor)
+}
+
+/// A pager reading output written in-process.
+///
+/// If the user quits the pager early, the rest of the output is discarded.
+pub struct Pager {
+    child: Child,
+    input: Option<ChildStdin>,
+}
+
+impl Pager {
+    /// Start the pager.
+    pub fn start(pager: Option<&str>) -> Result<Pager, ProgramError> {
+        let mut cmd = pager_command(pager)?;
+        if env::var_os("LESS").is_none() {
+            cmd.env("LESS", "FRX");
+        }
+        let mut child = cmd.stdin(Stdio::piped()).spawn()?;
+        let input = child.stdin.take();
+        Ok(Pager { child, input })
+    }
+
+    /// Close the pager's input and wait for it to exit.
+    pub fn finish(mut self) -> Result<(), ProgramError> {
+        drop(self.input.take());
+        let res = self.child.wait()?;
+        ProgramError::check(res)
+    }
+}
+
+impl Write for Pager {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let Some(input) = &mut self.input else {
+            return Ok(buf.len());
+        };
+        match input.write(buf) {
+            Err(e) if e.kind() == ErrorKind::BrokenPipe => {
+                self.input = None;
+                Ok(buf.len())
+            }
+            res => res,
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.input {
+            Some(input) => input.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_pager_words() {
+        let cmd = pager_command(Some("less -R --prompt='file %f'")).unwrap();
+        assert_eq!(cmd.get_program(), "less");
+        let args: Vec<_> = cmd.get_args().collect();
+        assert_eq!(args, ["-R", "--prompt=file %f"]);
+    }
+
+    #[test]
+    fn rejects_invalid_pager() {
+        let err = pager_command(Some("less --prompt='file")).unwrap_err();
+        assert!(matches!(err, ProgramError::InvalidCommand(..)));
+    }
 }
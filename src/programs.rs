@@ -1,8 +1,13 @@
 //! Utilities for finding and invoking external programs.
 use std::ffi::OsString;
+use std::io::Read;
 use std::path::PathBuf;
-use std::process::{Command, ExitStatus};
+use std::process::{Command, ExitStatus, Output, Stdio};
+use std::sync::mpsc::channel;
+use std::thread::{sleep, spawn};
+use std::time::{Duration, Instant};
 
+use log::*;
 use thiserror::Error;
 use which::{which, Error as WhichError};
 
@@ -10,8 +15,12 @@ use which::{which, Error as WhichError};
 pub enum ProgramError {
     #[error("error searching for external program")]
     ProgramSearchError,
+    #[error("invalid command {0:?}: {1}")]
+    InvalidCommand(String, #[source] shell_words::ParseError),
     #[error("program exited with nonzero exit {0}")]
     ExitError(ExitStatus),
+    #[error("program did not finish within {0:?}")]
+    Timeout(Duration),
     #[error("I/O error running program")]
     IOError(#[from] std::io::Error),
 }
@@ -23,6 +32,18 @@ impl ProgramError {
             Err(ProgramError::ExitError(status))
         }
     }
+
+    /// Check an exit status, accepting the nonzero codes a program uses to
+    /// report minor problems.
+    pub fn check_minor(status: ExitStatus, minor: &[i32]) -> Result<(), ProgramError> {
+        match status.code() {
+            Some(code) if code != 0 && minor.contains(&code) => {
+                debug!("program reported minor problems (exit code {})", code);
+                Ok(())
+            }
+            _ => ProgramError::check(status),
+        }
+    }
 }
 
 /// Find a program.
@@ -41,6 +62,40 @@ pub fn run_command(mut cmd: Command) -> Result<(), ProgramError> {
     ProgramError::check(res)
 }
 
+/// Run a program without input and capture its output, killing it if it does
+/// not finish within the time limit.
+pub fn run_with_timeout(mut cmd: Command, limit: Duration) -> Result<Output, ProgramError> {
+    let mut child = cmd.stdin(Stdio::null()).stdout(Stdio::piped()).spawn()?;
+    // read on another thread so a full pipe cannot stall the program
+    let mut stdout = child.stdout.take().expect("piped stdout");
+    let (send, recv) = channel();
+    spawn(move || {
+        let mut buf = Vec::new();
+        let _ = send.send(stdout.read_to_end(&mut buf).map(|_| buf));
+    });
+    let start = Instant::now();
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if start.elapsed() >= limit {
+            child.kill()?;
+            child.wait()?;
+            return Err(ProgramError::Timeout(limit));
+        }
+        sleep(Duration::from_millis(10));
+    };
+    // a process the program left behind may still hold the pipe open
+    let stdout = recv
+        .recv_timeout(limit.saturating_sub(start.elapsed()))
+        .map_err(|_| ProgramError::Timeout(limit))??;
+    Ok(Output {
+        status,
+        stdout,
+        stderr: Vec::new(),
+    })
+}
+
 pub fn program_name(cmd: &Command) -> String {
     let exe = cmd.get_program();
     let path = PathBuf::from(exe);
@@ -50,3 +105,16 @@ pub fn program_name(cmd: &Command) -> String {
         "<unknown>".to_string()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_minor_exit_codes() {
+        let status = Command::new("sh").args(["-c", "exit 1"]).status().unwrap();
+        assert!(ProgramError::check_minor(status, &[1]).is_ok());
+        assert!(ProgramError::check_minor(status, &[2]).is_err());
+        assert!(ProgramError::check(status).is_err());
+    }
+}
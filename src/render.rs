@@ -0,0 +1,614 @@
+//! Rendering structured view output.
+use std::fmt::{self, Write as _};
+use std::fs::{read, File};
+use std::io::{self, copy, stdout, BufRead, BufReader, IsTerminal, Write};
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use bat::line_range::{LineRange, LineRanges};
+use bat::{PagingMode, PrettyPrinter};
+use serde_json::{json, Value as Json};
+use textwrap::core::display_width;
+
+use crate::highlight::{highlight_lines, SyntaxHint};
+use crate::interface::{
+    Align, Block, Field, FileRequest, MetaInfo, OutputFormat, Passthrough, Table, Value, ViewError,
+    ViewOptions, ViewOutput,
+};
+use crate::pager::{page_command, page_file, Pager};
+use crate::programs::{program_name, ProgramError};
+use crate::styling::{color_enabled, styled, true_color, FIELD_NAME, FILE_SIZE, FILE_TYPE};
+
+/// Render view output in the requested format.
+///
+/// Errors after some output has been written are wrapped in
+/// [ViewError::AfterOutput], as the output cannot be taken back.
+pub fn render_output(
+    req: &FileRequest,
+    output: ViewOutput,
+    options: &ViewOptions,
+) -> Result<(), ViewError> {
+    match options.format {
+        OutputFormat::Text => render_text(output, true, options),
+        OutputFormat::Plain => render_text(output, false, options),
+        OutputFormat::Json => {
+            let doc = output_json(req, output)?;
+            let text = serde_json::to_string_pretty(&doc).map_err(ViewError::wrap)?;
+            println!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+/// Render output as text.  On the terminal, source is highlighted and the
+/// output is paged if the view requests it.
+fn render_text(output: ViewOutput, terminal: bool, options: &ViewOptions) -> Result<(), ViewError> {
+    // internal passthroughs write to standard output themselves, so they
+    // cannot be paged with the rest of the output
+    let paged = terminal && output.paged && !has_internal(&output.blocks);
+    let mut blocks = output.blocks;
+    if blocks.len() == 1 {
+        match blocks.pop() {
+            // a lone passthrough can stream straight to the pager
+            Some(Block::Raw(raw)) if paged => return page_passthrough(raw, options),
+            // and bat can highlight and page a lone source file
+            Some(Block::Source { path, lines }) if terminal => {
+                return print_source(&path, lines, paged, options)
+            }
+            Some(block) => blocks.push(block),
+            None => (),
+        }
+    }
+
+    let mut pager = if paged && stdout().is_terminal() {
+        Some(Pager::start(options.pager.as_deref())?)
+    } else {
+        None
+    };
+    let piped = pager.is_some();
+    let mut stdout = stdout().lock();
+    let mut out = TrackedOutput {
+        out: match &mut pager {
+            Some(pager) => pager,
+            None => &mut stdout,
+        },
+        started: false,
+    };
+    let res = blocks
+        .into_iter()
+        .try_for_each(|block| render_block(&mut out, block, terminal, piped, options))
+        .and_then(|()| Ok(out.flush()?));
+    let res = match res {
+        Err(e) if out.started => Err(ViewError::AfterOutput(Box::new(e))),
+        res => res,
+    };
+    if let Some(pager) = pager {
+        pager.finish()?;
+    }
+    res
+}
+
+/// Output that notes whether anything has been written to it.
+struct TrackedOutput<'a> {
+    out: &'a mut dyn Write,
+    /// Whether output has been written, or a program writing directly to
+    /// standard output has been started.
+    started: bool,
+}
+
+impl Write for TrackedOutput<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.started |= !buf.is_empty();
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Render a block to the output as it is produced.  When the output is
+/// piped to a pager, passthrough commands are piped through it as well so
+/// everything reaches the pager in order.
+fn render_block(
+    out: &mut TrackedOutput,
+    block: Block,
+    terminal: bool,
+    piped: bool,
+    options: &ViewOptions,
+) -> Result<(), ViewError> {
+    let mut text = String::new();
+    match block {
+        Block::Meta(info) => write_meta(&mut text, &info).expect("string write failed"),
+        Block::Section(title, blocks) => {
+            writeln!(out, "{}", styled(format!("{}:", title), &FIELD_NAME))?;
+            for block in blocks {
+                render_block(out, block, terminal, piped, options)?;
+            }
+        }
+        Block::Fields(fields) => {
+            for field in &fields {
+                write_field(&mut text, field, 0).expect("string write failed");
+            }
+        }
+        Block::Table(table) => write_table(&mut text, &table).expect("string write failed"),
+        Block::Text(lines) => {
+            for line in lines {
+                writeln!(out, "{}", line)?;
+            }
+        }
+        Block::Source { path, lines } => write_source(out, &path, lines, terminal, options)?,
+        Block::Raw(Passthrough::Command(mut cmd, minor)) if piped => {
+            let mut child = cmd.stdin(Stdio::null()).stdout(Stdio::piped()).spawn()?;
+            let mut data = child.stdout.take().expect("no stdout");
+            copy(&mut data, out)?;
+            ProgramError::check_minor(child.wait()?, minor)?;
+        }
+        Block::Raw(Passthrough::Command(mut cmd, minor)) => {
+            out.flush()?;
+            let mut child = cmd.spawn()?;
+            out.started = true;
+            ProgramError::check_minor(child.wait()?, minor)?;
+        }
+        Block::Raw(Passthrough::File(path)) => {
+            copy(&mut File::open(&path)?, out)?;
+        }
+        Block::Raw(Passthrough::Internal(func)) => {
+            out.flush()?;
+            out.started = true;
+            func()?;
+        }
+    }
+    out.write_all(text.as_bytes())?;
+    Ok(())
+}
+
+/// Check whether any block is an internal passthrough.
+fn has_internal(blocks: &[Block]) -> bool {
+    blocks.iter().any(|block| match block {
+        Block::Section(_, blocks) => has_internal(blocks),
+        Block::Raw(Passthrough::Internal(_)) => true,
+        _ => false,
+    })
+}
+
+/// Page a passthrough.  Programs that cannot be started have written
+/// nothing, but other failures come after output reached the pager.
+fn page_passthrough(raw: Passthrough, options: &ViewOptions) -> Result<(), ViewError> {
+    let pager = options.pager.as_deref();
+    let res = match raw {
+        Passthrough::Command(cmd, minor) => page_command(cmd, minor, pager),
+        Passthrough::File(path) => page_file(&path, pager),
+        Passthrough::Internal(func) => {
+            return func().map_err(|e| ViewError::AfterOutput(Box::new(e)))
+        }
+    };
+    match res {
+        Err(e @ (ProgramError::IOError(_) | ProgramError::InvalidCommand(..))) => Err(e.into()),
+        Err(e) => Err(ViewError::AfterOutput(Box::new(e.into()))),
+        Ok(()) => Ok(()),
+    }
+}
+
+/// Print a source file with `bat`, which reads only the lines it shows.
+fn print_source(
+    path: &Path,
+    lines: Option<usize>,
+    paged: bool,
+    options: &ViewOptions,
+) -> Result<(), ViewError> {
+    let mut printer = PrettyPrinter::new();
+    printer
+        .input_file(path)
+        .colored_output(color_enabled())
+        .true_color(true_color());
+    if let Some(lines) = lines {
+        printer.line_ranges(LineRanges::from(vec![LineRange::new(1, lines)]));
+    }
+    if let Some(theme) = &options.theme {
+        printer.theme(theme);
+    }
+    if let Some(pager) = &options.pager {
+        printer.pager(pager);
+    }
+    printer.paging_mode(if paged {
+        PagingMode::QuitIfOneScreen
+    } else {
+        PagingMode::Never
+    });
+    printer.print().map_err(ViewError::wrap)?;
+    Ok(())
+}
+
+/// Write the lines of a source file, highlighting them on the terminal.
+fn write_source(
+    out: &mut impl Write,
+    path: &Path,
+    limit: Option<usize>,
+    terminal: bool,
+    options: &ViewOptions,
+) -> Result<(), ViewError> {
+    let lines = source_lines(path, limit)?;
+    if terminal {
+        let hint = SyntaxHint::Path(path);
+        highlight_lines(lines, hint, options.theme.as_deref(), |line| {
+            writeln!(out, "{}", line)
+        })?;
+    } else {
+        for line in lines {
+            writeln!(out, "{}", line?)?;
+        }
+    }
+    Ok(())
+}
+
+/// Read the lines of a source file as they are needed, up to an optional
+/// limit.
+fn source_lines(
+    path: &Path,
+    limit: Option<usize>,
+) -> io::Result<impl Iterator<Item = io::Result<String>>> {
+    let file = BufReader::new(File::open(path)?);
+    let lines = file.split(b'\n').take(limit.unwrap_or(usize::MAX));
+    Ok(lines.map(|line| {
+        let line = line?;
+        let line = line.strip_suffix(b"\r").unwrap_or(&line);
+        Ok(String::from_utf8_lossy(line).into_owned())
+    }))
+}
+
+/// Write a metadata headline and fields.
+fn write_meta(out: &mut String, info: &MetaInfo) -> fmt::Result {
+    write!(out, "{}", styled(&info.description, &FILE_TYPE))?;
+    if let Some(summary) = &info.summary {
+        let conj = match summary.value {
+            Value::Duration(_) => "of",
+            _ => "with",
+        };
+        write!(
+            out,
+            " {} {}",
+            conj,
+            styled(format_inline(&summary.value), &FILE_SIZE)
+        )?;
+    }
+    if let Some(size) = info.size {
+        write!(
+            out,
+            " ({})",
+            styled(format!("{}", friendly::bytes(size)), &FILE_SIZE)
+        )?;
+    }
+    writeln!(out)?;
+
+    for field in &info.fields {
+        write_field(out, field, 0)?;
+    }
+    Ok(())
+}
+
+fn write_field(out: &mut String, field: &Field, indent: usize) -> fmt::Result {
+    let pad = "  ".repeat(indent);
+    let label = styled(&field.label, &FIELD_NAME);
+    match &field.value {
+        Value::List(items) => {
+            writeln!(out, "{}{}:", pad, label)?;
+            for item in items {
+                write_item(out, item, indent + 1)?;
+            }
+        }
+        Value::Record(fields) => {
+            writeln!(out, "{}{}:", pad, label)?;
+            for f in fields {
+                write_field(out, f, indent + 1)?;
+            }
+        }
+        v => writeln!(out, "{}{}: {}", pad, label, format_inline(v))?,
+    }
+    Ok(())
+}
+
+fn write_item(out: &mut String, item: &Value, indent: usize) -> fmt::Result {
+    let pad = "  ".repeat(indent);
+    match item {
+        Value::List(items) => {
+            for i in items {
+                write_item(out, i, indent + 1)?;
+            }
+        }
+        Value::Record(fields) => {
+            for f in fields {
+                write_field(out, f, indent)?;
+            }
+        }
+        v => writeln!(out, "{}{}", pad, format_inline(v))?,
+    }
+    Ok(())
+}
+
+/// Write a table with aligned columns.
+fn write_table(out: &mut String, table: &Table) -> fmt::Result {
+    let mut rows: Vec<Vec<String>> = Vec::with_capacity(table.rows.len() + 1);
+    if table.headings {
+        rows.push(
+            table
+                .columns
+                .iter()
+                .map(|c| styled(&c.name, &FIELD_NAME).to_string())
+                .collect(),
+        );
+    }
+    rows.extend(
+        table
+            .rows
+            .iter()
+            .map(|r| r.iter().map(format_inline).collect()),
+    );
+
+    let mut widths = vec![0; table.columns.len()];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(display_width(cell));
+        }
+    }
+
+    for row in &rows {
+        let mut line = String::new();
+        for (i, (col, width)) in table.columns.iter().zip(&widths).enumerate() {
+            let cell = row.get(i).map(|c| c.as_str()).unwrap_or_default();
+            let pad = " ".repeat(width - display_width(cell));
+            if i > 0 {
+                line.push_str("  ");
+            }
+            match col.align {
+                Align::Left => {
+                    line.push_str(cell);
+                    line.push_str(&pad);
+                }
+                Align::Right => {
+                    line.push_str(&pad);
+                    line.push_str(cell);
+                }
+            }
+        }
+        writeln!(out, "{}", line.trim_end())?;
+    }
+    Ok(())
+}
+
+/// Format a value for display on a single line.
+fn format_inline(value: &Value) -> String {
+    match value {
+        Value::Text(s) => s.clone(),
+        Value::Integer(n) => n.to_string(),
+        Value::Count(n, unit) => format!("{} {}", friendly::integer(*n), unit),
+        Value::Quantity(x, unit) => format!("{} {}", format_float(*x), unit),
+        Value::Bytes(n) => format!("{}", friendly::bytes(*n)),
+        Value::Duration(d) => format_duration(*d),
+        Value::Dimensions(w, h) => format!("{}x{} pixels", w, h),
+        Value::Styled(s, style) => styled(s, style).to_string(),
+        Value::List(items) => items
+            .iter()
+            .map(format_inline)
+            .collect::<Vec<_>>()
+            .join(", "),
+        Value::Record(fields) => fields
+            .iter()
+            .map(|f| format!("{}: {}", f.label, format_inline(&f.value)))
+            .collect::<Vec<_>>()
+            .join(", "),
+    }
+}
+
+/// Format a float with up to 3 decimal places.
+fn format_float(x: f64) -> String {
+    let s = format!("{:.3}", x);
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+/// Format a duration as `[h:]mm:ss`.
+fn format_duration(dur: Duration) -> String {
+    let secs = dur.as_secs();
+    let (h, m, s) = (secs / 3600, (secs / 60) % 60, secs % 60);
+    if h > 0 {
+        format!("{}:{:02}:{:02}", h, m, s)
+    } else {
+        format!("{}:{:02}", m, s)
+    }
+}
+
+/// Convert view output to a JSON document.
+///
+/// Passthrough output from programs and files is captured as text.
+fn output_json(req: &FileRequest, output: ViewOutput) -> Result<Json, ViewError> {
+    let blocks = output
+        .blocks
+        .into_iter()
+        .map(block_json)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(json!({
+        "path": req.path.display().to_string(),
+        "mime_type": req.mime_type,
+        "blocks": blocks,
+    }))
+}
+
+fn block_json(block: Block) -> Result<Json, ViewError> {
+    Ok(match block {
+        Block::Meta(info) => json!({
+            "type": "meta",
+            "description": info.description,
+            "size": info.size,
+            "fields": fields_json(info.summary.iter().chain(info.fields.iter())),
+        }),
+        Block::Section(title, blocks) => json!({
+            "type": "section",
+            "title": title,
+            "blocks": blocks.into_iter().map(block_json).collect::<Result<Vec<_>, _>>()?,
+        }),
+        Block::Fields(fields) => json!({
+            "type": "fields",
+            "fields": fields_json(fields.iter()),
+        }),
+        Block::Table(table) => json!({
+            "type": "table",
+            "columns": table.columns.iter().map(|c| &c.name).collect::<Vec<_>>(),
+            "rows": table
+                .rows
+                .iter()
+                .map(|r| r.iter().map(value_json).collect::<Vec<_>>())
+                .collect::<Vec<_>>(),
+        }),
+        Block::Text(lines) => json!({"type": "text", "lines": lines}),
+        Block::Source { path, lines } => json!({
+            "type": "text",
+            "lines": source_lines(&path, lines)?.collect::<io::Result<Vec<_>>>()?,
+        }),
+        Block::Raw(Passthrough::Command(mut cmd, minor)) => {
+            let output = cmd.stdin(Stdio::null()).output()?;
+            ProgramError::check_minor(output.status, minor)?;
+            json!({
+                "type": "raw",
+                "program": program_name(&cmd),
+                "output": String::from_utf8_lossy(&output.stdout),
+            })
+        }
+        Block::Raw(Passthrough::File(path)) => json!({
+            "type": "raw",
+            "output": String::from_utf8_lossy(&read(&path)?),
+        }),
+        Block::Raw(Passthrough::Internal(_)) => {
+            return Err("this view cannot be converted to JSON".into())
+        }
+    })
+}
+
+/// Convert fields to a list of labels and values, keeping their order (and
+/// repeated labels).
+fn fields_json<'a, I: Iterator<Item = &'a Field>>(fields: I) -> Json {
+    fields
+        .map(|field| {
+            json!({
+                "key": field.key,
+                "label": field.label,
+                "value": value_json(&field.value),
+            })
+        })
+        .collect()
+}
+
+fn value_json(value: &Value) -> Json {
+    match value {
+        Value::Text(s) | Value::Styled(s, _) => json!(s),
+        Value::Integer(n) => json!(n),
+        Value::Count(n, _) | Value::Bytes(n) => json!(n),
+        Value::Quantity(x, _) => json!(x),
+        Value::Duration(d) => json!(d.as_secs_f64()),
+        Value::Dimensions(w, h) => json!({"width": w, "height": h}),
+        Value::List(items) => Json::Array(items.iter().map(value_json).collect()),
+        Value::Record(fields) => fields_json(fields.iter()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::interface::Column;
+
+    fn request() -> FileRequest {
+        FileRequest {
+            path: PathBuf::from("test.dat"),
+            meta: None,
+            link_target: None,
+            mime_type: "application/x-test".into(),
+        }
+    }
+
+    #[test]
+    fn json_document() {
+        let mut info = MetaInfo::new("Test file");
+        info.size = Some(42);
+        info.summary = Some(Field::new(
+            "duration",
+            "Duration",
+            Value::Duration(Duration::from_secs(3)),
+        ));
+        info.add("author", "Author", "A");
+        info.add("author", "Author", "B");
+        let table = Table {
+            columns: vec![
+                Column::new("Name", Align::Left),
+                Column::new("Count", Align::Right),
+            ],
+            rows: vec![vec![Value::Text("x".into()), Value::Integer(1)]],
+            headings: true,
+        };
+        let output = ViewOutput::new(vec![
+            Block::Meta(info),
+            Block::Section(
+                "Data".into(),
+                vec![Block::Table(table), Block::Text(vec!["line".into()])],
+            ),
+            Block::Fields(vec![
+                Field::new("size", "Size", Value::Dimensions(2, 3)),
+                Field::from_label("user.Origin-URL", "x"),
+            ]),
+        ]);
+
+        let doc = output_json(&request(), output).unwrap();
+        assert_eq!(
+            doc,
+            json!({
+                "path": "test.dat",
+                "mime_type": "application/x-test",
+                "blocks": [
+                    {
+                        "type": "meta",
+                        "description": "Test file",
+                        "size": 42,
+                        "fields": [
+                            {"key": "duration", "label": "Duration", "value": 3.0},
+                            {"key": "author", "label": "Author", "value": "A"},
+                            {"key": "author", "label": "Author", "value": "B"},
+                        ],
+                    },
+                    {
+                        "type": "section",
+                        "title": "Data",
+                        "blocks": [
+                            {"type": "table", "columns": ["Name", "Count"], "rows": [["x", 1]]},
+                            {"type": "text", "lines": ["line"]},
+                        ],
+                    },
+                    {
+                        "type": "fields",
+                        "fields": [
+                            {"key": "size", "label": "Size", "value": {"width": 2, "height": 3}},
+                            {"key": "user_origin_url", "label": "user.Origin-URL", "value": "x"},
+                        ],
+                    },
+                ],
+            })
+        );
+    }
+
+    #[test]
+    fn reads_limited_source_lines() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(b"one\r\ntw\xffo\nthree\n").unwrap();
+        let lines: Vec<_> = source_lines(file.path(), Some(2))
+            .unwrap()
+            .collect::<io::Result<_>>()
+            .unwrap();
+        assert_eq!(lines, vec!["one", "tw\u{fffd}o"]);
+    }
+
+    #[test]
+    fn json_rejects_internal_output() {
+        let output = ViewOutput::new(vec![Block::Raw(Passthrough::Internal(Box::new(|| Ok(()))))]);
+        assert!(output_json(&request(), output).is_err());
+    }
+}
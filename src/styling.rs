@@ -1,15 +1,26 @@
 use std::{
+    env,
     fmt::{Display, Formatter},
     sync::atomic::{AtomicBool, Ordering},
 };
 
 use anstyle::Style;
 use anstyle::{AnsiColor, Color};
+use terminal_size::{terminal_size, Width};
 
 pub static FIELD_NAME: Style = Style::new().bold();
 pub static EXTRA_MARKER: Style = Style::new().dimmed();
 pub static FILE_SIZE: Style = Style::new().fg_color(acolor(AnsiColor::Cyan));
 pub static FILE_TYPE: Style = Style::new().fg_color(acolor(AnsiColor::Magenta));
+pub static LINK: Style = Style::new().underline().fg_color(acolor(AnsiColor::Cyan));
+pub static CODE: Style = Style::new().fg_color(acolor(AnsiColor::Yellow));
+pub static DIRECTORY: Style = Style::new().bold().fg_color(acolor(AnsiColor::Blue));
+pub static SYMLINK: Style = Style::new().fg_color(acolor(AnsiColor::Cyan));
+pub static EXECUTABLE: Style = Style::new().bold().fg_color(acolor(AnsiColor::Green));
+pub static GIT_MODIFIED: Style = Style::new().fg_color(acolor(AnsiColor::Yellow));
+pub static GIT_NEW: Style = Style::new().fg_color(acolor(AnsiColor::Green));
+pub static GIT_IGNORED: Style = Style::new().dimmed();
+pub static MISSING: Style = Style::new().invert().fg_color(acolor(AnsiColor::Red));
 
 static COLOR_ENABLED: AtomicBool = AtomicBool::new(false);
 
@@ -21,10 +32,29 @@ pub fn color_enabled() -> bool {
     COLOR_ENABLED.load(Ordering::Relaxed)
 }
 
+/// Check whether the terminal supports 24-bit color, following `bat`'s use
+/// of `$COLORTERM`.
+pub fn true_color() -> bool {
+    env::var("COLORTERM")
+        .map(|ct| ct == "truecolor" || ct == "24bit")
+        .unwrap_or(false)
+}
+
+/// Get the width to use for wrapping text output.
+///
+/// This is the terminal width if standard output is a terminal, and 80
+/// columns otherwise.
+pub fn term_width() -> usize {
+    match terminal_size() {
+        Some((Width(w), _)) => w as usize,
+        None => 80,
+    }
+}
+
 /// Wrap text in styling.
 pub fn styled<S: AsRef<str>>(text: S, style: &Style) -> StyleDisplay {
     let style = if color_enabled() {
-        style.clone()
+        *style
     } else {
         Style::new()
     };
@@ -34,13 +64,22 @@ pub fn styled<S: AsRef<str>>(text: S, style: &Style) -> StyleDisplay {
     }
 }
 
+/// Combine two styles, with the second taking precedence for colors.
+pub fn combine_styles(base: &Style, over: &Style) -> Style {
+    let mut style = base.effects(base.get_effects() | over.get_effects());
+    if let Some(color) = over.get_fg_color() {
+        style = style.fg_color(Some(color));
+    }
+    style
+}
+
 /// Wrapper to display a styled texts.
 pub struct StyleDisplay {
     text: String,
     style: Style,
 }
 
-impl<'t> Display for StyleDisplay {
+impl Display for StyleDisplay {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}{}{:#}", self.style, self.text, self.style)
     }